@@ -1,41 +1,760 @@
-use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::collections::VecDeque;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs, UdpSocket};
+use std::path::Path;
 use std::str::Split;
-use std::time::{Duration, Instant};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
-static PORT: u16 = 4000;
-static LOCAL_IP: Ipv4Addr = Ipv4Addr::new(192, 168, 4, 2); // TODO: make this configurable
-static REMOTE_IP: Ipv4Addr = Ipv4Addr::new(192, 168, 4, 1); // TODO: make this configurable
+use serde::{Deserialize, Serialize};
+
+use crate::decoder::{BeU16Decoder, PacketDecoder, TextDecoder};
+use crate::display::{FULL_SCALE, VREF_MV};
+use crate::export::{CsvRecorder, CsvReplay, Decimation, ReplayConfig};
+use crate::simulation::{SimulatedDevice, SimulationConfig};
+use crate::transport::{FifoTransport, Transport};
+
+pub static LOCAL_PORT: u16 = 4000; // 0 lets the OS pick a free port
+pub static REMOTE_PORT: u16 = 4000;
+static LOCAL_IP: Ipv4Addr = Ipv4Addr::new(192, 168, 4, 2);
+static REMOTE_IP: Ipv4Addr = Ipv4Addr::new(192, 168, 4, 1);
 
 static POLL_DELAY: Duration = Duration::from_millis(1); // prevent interface spam
+static READ_TIMEOUT: Duration = Duration::from_millis(500); // an unplugged device never replies
+static INIT_ACK_TIMEOUT: Duration = Duration::from_millis(200);
+static INIT_RETRY_DELAY: Duration = Duration::from_millis(50);
+/// times init is sent before the handshake gives up, unless configured otherwise
+pub static INIT_ATTEMPTS: u32 = 3;
+static CONNECTION_HISTORY_LEN: usize = 32;
+/// wait after the first failed poll, doubling with each failure after it
+static RETRY_BACKOFF_MIN: Duration = Duration::from_millis(100);
+static RETRY_BACKOFF_MAX: Duration = Duration::from_secs(5);
+/// how many of the latest intervals between samples the interval stats cover
+static INTERVAL_WINDOW: usize = 100;
+/// an interval this many times the mean counts as a hitch
+static HITCH_FACTOR: f64 = 3.0;
+/// polls further apart than this and the device has likely given up on us, by default
+pub static STALL_TIMEOUT: Duration = Duration::from_secs(2);
+/// how far back history() reaches by default
+pub static HISTORY_WINDOW: Duration = Duration::from_secs(10);
+/// UDP payload that fits typical 1500 byte MTUs with room for headers and tunnels
+pub static FRAGMENTATION_THRESHOLD: usize = 1400;
+/// the stock firmware reads a0..a3
+pub static DEFAULT_CHANNELS: usize = 4;
+/// most channels a frame can carry, per-channel settings are kept for this many
+pub const MAX_CHANNELS: usize = 16;
+/// the status, sequence and checksum bytes after a binary frame's values
+static MAX_TRAILER_LEN: usize = 3;
 
-#[derive(Debug)]
+/// one reading of every channel, a0 first
+#[derive(Debug, Clone, PartialEq)]
 pub struct AnalogValues {
-    pub a0: u16,
-    pub a1: u16,
-    pub a2: u16,
-    pub a3: u16,
+    pub channels: Vec<u16>,
+}
+
+impl AnalogValues {
+    pub fn len(&self) -> usize {
+        self.channels.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.channels.is_empty()
+    }
+
+    /// each channel's value, a0 first
+    pub fn iter(&self) -> std::slice::Iter<'_, u16> {
+        self.channels.iter()
+    }
+}
+
+/// the value of a channel, panics past the last one like a slice
+impl std::ops::Index<usize> for AnalogValues {
+    type Output = u16;
+
+    fn index(&self, channel: usize) -> &u16 {
+        &self.channels[channel]
+    }
+}
+
+impl<'a> IntoIterator for &'a AnalogValues {
+    type Item = &'a u16;
+    type IntoIter = std::slice::Iter<'a, u16>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// when a reading was taken and what it was
+pub type HistorySample = (Instant, AnalogValues);
+
+/// linear conversion of a channel's raw counts into millivolts
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Calibration {
+    /// mV per count
+    pub scale: f32,
+    /// mV at a count of 0
+    pub offset: f32,
+}
+
+/// an ideal 12-bit ADC against the nominal reference
+impl Default for Calibration {
+    fn default() -> Self {
+        Calibration::ideal(VREF_MV)
+    }
+}
+
+impl Calibration {
+    /// an ideal 12-bit ADC against `vref_mv`, raw * vref_mv / 4096
+    pub fn ideal(vref_mv: f32) -> Self {
+        Calibration {
+            scale: vref_mv / FULL_SCALE,
+            offset: 0.0,
+        }
+    }
+
+    pub fn to_mv(self, raw: f32) -> f32 {
+        raw * self.scale + self.offset
+    }
+
+    /// the raw count that would read as `mv`
+    pub fn to_raw(self, mv: f32) -> f32 {
+        (mv - self.offset) / self.scale
+    }
+}
+
+/// `<mV per count>:<offset mV>`
+impl std::str::FromStr for Calibration {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (scale, offset) = s
+            .split_once(':')
+            .ok_or(format!("calibration {:?} is not scale:offset", s))?;
+        let scale: f32 = scale
+            .trim()
+            .parse()
+            .map_err(|e| format!("invalid scale {:?}: {:?}", scale, e))?;
+        let offset = offset
+            .trim()
+            .parse()
+            .map_err(|e| format!("invalid offset {:?}: {:?}", offset, e))?;
+
+        if scale == 0.0 {
+            return Err(format!("calibration {:?} has a zero scale", s));
+        }
+        Ok(Calibration { scale, offset })
+    }
+}
+
+/// band a channel's displayed value has to stay inside, either side may be open
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Thresholds {
+    pub low: Option<f32>,
+    pub high: Option<f32>,
+}
+
+impl Thresholds {
+    /// whether `value` is outside the band, never with no thresholds set
+    pub fn exceeded(self, value: f32) -> bool {
+        self.low.is_some_and(|low| value < low) || self.high.is_some_and(|high| value > high)
+    }
+}
+
+/// `<low>:<high>`, leave a side empty to not limit it, e.g. `:3000`
+impl std::str::FromStr for Thresholds {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (low, high) = s
+            .split_once(':')
+            .ok_or(format!("thresholds {:?} are not low:high", s))?;
+        let parse = |side: &str, name: &str| match side.trim() {
+            "" => Ok(None),
+            side => side
+                .parse()
+                .map(Some)
+                .map_err(|e| format!("invalid {} threshold {:?}: {:?}", name, side, e)),
+        };
+        let thresholds = Thresholds {
+            low: parse(low, "low")?,
+            high: parse(high, "high")?,
+        };
+
+        if let (Some(low), Some(high)) = (thresholds.low, thresholds.high) {
+            if low > high {
+                return Err(format!("thresholds {:?} leave no valid values", s));
+            }
+        }
+        Ok(thresholds)
+    }
+}
+
+/// output voltage of the RP2040's temperature sensor at 27 °C
+static TEMP_SENSOR_V27: f32 = 0.706;
+/// how much the sensor's output drops per °C, in V
+static TEMP_SENSOR_SLOPE: f32 = 0.001721;
+/// what a logarithmic channel shows for zero and anything too small to tell from it, in dB
+static LOG_FLOOR_DB: f32 = -120.0;
+
+/// what a channel shows, worked out from its calibrated value
+/// the calibration still handles the ADC, this turns mV into whatever the channel measures
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplayTransform {
+    /// the calibrated value as it is
+    #[default]
+    Linear,
+    /// the RP2040's on-die temperature sensor, in °C
+    Temperature,
+    /// `calibrated * scale + offset`
+    Custom(f32, f32),
+    /// `20 * log10(calibrated / reference)` in dB, e.g. dBFS with the full scale as the reference
+    /// the needle stays linear, only the readings are in dB
+    Logarithmic { reference: f32 },
+}
+
+impl DisplayTransform {
+    pub fn apply(self, calibrated: f32) -> f32 {
+        match self {
+            DisplayTransform::Linear => calibrated,
+            DisplayTransform::Temperature => {
+                27.0 - (calibrated / 1000.0 - TEMP_SENSOR_V27) / TEMP_SENSOR_SLOPE
+            }
+            DisplayTransform::Custom(scale, offset) => calibrated * scale + offset,
+            DisplayTransform::Logarithmic { reference } => {
+                (20.0 * (calibrated / reference).log10()).max(LOG_FLOOR_DB)
+            }
+        }
+    }
+
+    /// the calibrated value that would show as `value`
+    pub fn invert(self, value: f32) -> f32 {
+        match self {
+            DisplayTransform::Linear => value,
+            DisplayTransform::Temperature => {
+                ((27.0 - value) * TEMP_SENSOR_SLOPE + TEMP_SENSOR_V27) * 1000.0
+            }
+            DisplayTransform::Custom(scale, offset) => (value - offset) / scale,
+            DisplayTransform::Logarithmic { reference } => reference * 10f32.powf(value / 20.0),
+        }
+    }
+
+    /// the unit the result is in, `None` if that's up to the channel's configured unit
+    pub fn unit(self) -> Option<&'static str> {
+        match self {
+            DisplayTransform::Temperature => Some("°C"),
+            DisplayTransform::Logarithmic { .. } => Some("dB"),
+            _ => None,
+        }
+    }
+}
+
+/// `linear`, `temperature`, `custom:<scale>:<offset>` or `log:<reference>`
+impl std::str::FromStr for DisplayTransform {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_lowercase().as_str() {
+            "linear" => Ok(DisplayTransform::Linear),
+            "temperature" => Ok(DisplayTransform::Temperature),
+            other if other.starts_with("log:") => {
+                let reference = &other["log:".len()..];
+                let reference: f32 = reference
+                    .trim()
+                    .parse()
+                    .map_err(|e| format!("invalid reference {:?}: {:?}", reference, e))?;
+
+                if reference <= 0.0 {
+                    return Err(format!(
+                        "display transform {:?} needs a positive reference",
+                        s
+                    ));
+                }
+                Ok(DisplayTransform::Logarithmic { reference })
+            }
+            other => {
+                let (scale, offset) = other
+                    .strip_prefix("custom:")
+                    .and_then(|rest| rest.split_once(':'))
+                    .ok_or(format!("unknown display transform {:?}", s))?;
+                let scale: f32 = scale
+                    .trim()
+                    .parse()
+                    .map_err(|e| format!("invalid scale {:?}: {:?}", scale, e))?;
+                let offset = offset
+                    .trim()
+                    .parse()
+                    .map_err(|e| format!("invalid offset {:?}: {:?}", offset, e))?;
+
+                if scale == 0.0 {
+                    return Err(format!("display transform {:?} has a zero scale", s));
+                }
+                Ok(DisplayTransform::Custom(scale, offset))
+            }
+        }
+    }
+}
+
+/// health bits the device reports in the optional status byte after the values
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct DeviceFlags(u8);
+
+impl DeviceFlags {
+    const OVER_TEMP: u8 = 1 << 0;
+    const LOW_BATTERY: u8 = 1 << 1;
+    const SENSOR_FAULT: u8 = 1 << 2;
+
+    pub fn from_bits(bits: u8) -> Self {
+        DeviceFlags(bits)
+    }
+
+    pub fn over_temp(&self) -> bool {
+        self.0 & Self::OVER_TEMP != 0
+    }
+
+    pub fn low_battery(&self) -> bool {
+        self.0 & Self::LOW_BATTERY != 0
+    }
+
+    pub fn sensor_fault(&self) -> bool {
+        self.0 & Self::SENSOR_FAULT != 0
+    }
+}
+
+/// capabilities the device reports in its init acknowledgement
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeviceInfo {
+    pub channels: u8,
+    pub bit_depth: u8,
+    pub status_byte: bool,
+}
+
+impl DeviceInfo {
+    /// parse an init acknowledgement of the form `ack:<channels>:<bit depth>:<status byte 0/1>`
+    /// a bare `ack` from older firmware carries no info and gives `None`
+    fn from_ack(ack: &[u8]) -> Result<Option<Self>, ViewerBackendError> {
+        let ack = String::from_utf8_lossy(ack);
+        let mut split = ack.trim().split(':');
+        split.next(); // "ack"
+
+        if split.clone().next().is_none() {
+            return Ok(None);
+        }
+
+        let channels = ViewerBackend::parse_piece(&mut split, "channel count")?;
+        let bit_depth = ViewerBackend::parse_piece(&mut split, "bit depth")?;
+        let status_byte = ViewerBackend::parse_piece(&mut split, "status byte flag")?;
+
+        if channels == 0 || channels as usize > MAX_CHANNELS {
+            Err(ViewerBackendError::ParserError(format!(
+                "device claims {} channels, only 1 to {} are supported",
+                channels, MAX_CHANNELS
+            )))?
+        }
+        let bit_depth = u8::try_from(bit_depth).map_err(|_| {
+            ViewerBackendError::ParserError(format!("bit depth {} is out of range", bit_depth))
+        })?;
+
+        Ok(Some(DeviceInfo {
+            channels: channels as u8,
+            bit_depth,
+            status_byte: status_byte != 0,
+        }))
+    }
+}
+
+impl std::fmt::Display for DeviceInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "device: {} channels, {}-bit",
+            self.channels, self.bit_depth
+        )?;
+        if self.status_byte {
+            write!(f, ", status byte")?;
+        }
+        Ok(())
+    }
+}
+
+/// something that happened while setting up the link to the device
+#[derive(Debug, Clone, PartialEq)]
+pub enum ConnectionEventKind {
+    Opened(String), // what was opened, e.g. the bound address
+    Connected(SocketAddr),
+    ConnectFailed(String),
+    InitSent { attempt: u32 },
+    InitAcked,
+    InitFailed(String),
+    FirstPollOk,
+    FirstPollFailed(String),
+    FrameTimeout(Duration), // no valid frame for this long, the handshake is redone
+    PollGap(Duration),      // no poll sent for this long, the handshake is redone
+    FragmentationRisk { frame_len: usize, threshold: usize },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConnectionEvent {
+    pub at: SystemTime,
+    pub kind: ConnectionEventKind,
+}
+
+/// e.g. `14:03:27.120 UTC init sent (attempt 2)`
+impl std::fmt::Display for ConnectionEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let since_epoch = self.at.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let secs = since_epoch.as_secs() % 86400;
+        write!(
+            f,
+            "{:02}:{:02}:{:02}.{:03} UTC ",
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60,
+            since_epoch.subsec_millis()
+        )?;
+
+        match &self.kind {
+            ConnectionEventKind::Opened(what) => write!(f, "opened {}", what),
+            ConnectionEventKind::Connected(addr) => write!(f, "connected to {}", addr),
+            ConnectionEventKind::ConnectFailed(e) => write!(f, "connect failed: {}", e),
+            ConnectionEventKind::InitSent { attempt } => {
+                write!(f, "init sent (attempt {})", attempt)
+            }
+            ConnectionEventKind::InitAcked => write!(f, "init acknowledged"),
+            ConnectionEventKind::InitFailed(e) => write!(f, "init failed: {}", e),
+            ConnectionEventKind::FirstPollOk => write!(f, "first poll ok"),
+            ConnectionEventKind::FirstPollFailed(e) => write!(f, "first poll failed: {}", e),
+            ConnectionEventKind::FragmentationRisk {
+                frame_len,
+                threshold,
+            } => write!(
+                f,
+                "{} byte frames exceed {} bytes and may fragment",
+                frame_len, threshold
+            ),
+            ConnectionEventKind::FrameTimeout(after) => {
+                write!(
+                    f,
+                    "no valid frame for {:.1}s, reinitializing",
+                    after.as_secs_f32()
+                )
+            }
+            ConnectionEventKind::PollGap(gap) => {
+                write!(f, "no poll for {:.1}s, reinitializing", gap.as_secs_f32())
+            }
+        }
+    }
+}
+
+/// how the device encodes its replies to `poll`
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ProtocolMode {
+    /// big-endian u16s, see frame_len()
+    #[default]
+    Binary,
+    /// `a0:<value>:a1:<value>:...` in ASCII, as older firmware sends it
+    /// one reply per datagram, so only over UDP
+    Text,
+}
+
+impl ProtocolMode {
+    /// the built-in decoder for this protocol
+    pub fn decoder(self, extra: ExtraFields) -> Box<dyn PacketDecoder> {
+        match self {
+            ProtocolMode::Binary => Box::new(BeU16Decoder),
+            ProtocolMode::Text => Box::new(TextDecoder { extra }),
+        }
+    }
+}
+
+impl std::str::FromStr for ProtocolMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "binary" => Ok(ProtocolMode::Binary),
+            "text" => Ok(ProtocolMode::Text),
+            other => Err(format!(
+                "unknown protocol {:?}, expected binary or text",
+                other
+            )),
+        }
+    }
+}
+
+/// what the text decoder does with fields after the last channel
+/// newer firmware may append fields older viewers don't know about
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ExtraFields {
+    #[default]
+    Ignore,
+    Reject,
+}
+
+impl std::str::FromStr for ExtraFields {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "ignore" => Ok(ExtraFields::Ignore),
+            "reject" => Ok(ExtraFields::Reject),
+            other => Err(format!(
+                "unknown policy {:?}, expected ignore or reject",
+                other
+            )),
+        }
+    }
+}
+
+/// slowly adapting estimate of the span a channel moves through
+#[derive(Debug, Clone, Copy)]
+struct AutoRange {
+    min: f32,
+    max: f32,
+}
+
+impl AutoRange {
+    /// smallest span shown, so a flat signal doesn't blow up the scale
+    const MIN_SPAN: f32 = 16.0;
+    /// headroom on both ends, as a fraction of the span
+    const MARGIN: f32 = 0.1;
+
+    fn new(value: f32) -> Self {
+        AutoRange {
+            min: value,
+            max: value,
+        }
+    }
+
+    /// widen straight away to take in new extremes, narrow towards the value by `rate` per second
+    fn update(&mut self, value: f32, rate: f32, dt: f32) {
+        let k = (rate * dt).min(1.0);
+
+        if value < self.min {
+            self.min = value;
+        } else {
+            self.min += (value - self.min) * k;
+        }
+
+        if value > self.max {
+            self.max = value;
+        } else {
+            self.max -= (self.max - value) * k;
+        }
+    }
+
+    fn range(&self) -> (f32, f32) {
+        let center = (self.min + self.max) / 2.0;
+        let span = (self.max - self.min).max(Self::MIN_SPAN) * (1.0 + 2.0 * Self::MARGIN);
+        (center - span / 2.0, center + span / 2.0)
+    }
+}
+
+/// `ip:port` or `host:port`, e.g. `rp2040.local:4000`, IPv6 addresses go in brackets
+/// a name that resolves to several addresses uses the first
+pub fn resolve_addr(target: &str) -> Result<SocketAddr, ViewerBackendError> {
+    let target = target.trim();
+    if let Ok(addr) = target.parse() {
+        return Ok(addr);
+    }
+
+    let addr = target
+        .to_socket_addrs()
+        .map_err(ViewerBackendError::SocketError)?
+        .next()
+        .ok_or(ViewerBackendError::ParserError(format!(
+            "{:?} doesn't resolve to any address",
+            target
+        )))?;
+    log::info!("resolved {} to {}", target, addr);
+    Ok(addr)
+}
+
+/// the address to bind to reach `remote`
+/// a v4 socket can't talk to a v6 device or back, so `local` of the wrong family becomes "any" of the right one
+fn bind_addr(local: SocketAddr, remote: SocketAddr) -> SocketAddr {
+    if local.is_ipv4() == remote.is_ipv4() {
+        return local;
+    }
+
+    let any: IpAddr = match remote {
+        SocketAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+        SocketAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+    };
+    let addr = SocketAddr::new(any, local.port());
+    log::warn!(
+        "local address {} can't reach {}, binding to {} instead",
+        local,
+        remote,
+        addr
+    );
+    addr
+}
+
+/// where the backend listens, which device it polls and how often
+#[derive(Debug, Clone, PartialEq)]
+pub struct ViewerBackendConfig {
+    /// a port of 0 binds an ephemeral port so several viewers can run side by side
+    pub local_addr: SocketAddr,
+    pub remote_addr: SocketAddr,
+    /// minimum time between polls, so the interface isn't spammed
+    pub poll_delay: Duration,
+    /// how long one receive waits for the device, `None` blocks until it replies
+    pub read_timeout: Option<Duration>,
+}
+
+/// the addresses of the stock firmware's access point
+impl Default for ViewerBackendConfig {
+    fn default() -> Self {
+        ViewerBackendConfig {
+            local_addr: SocketAddr::from((LOCAL_IP, LOCAL_PORT)),
+            remote_addr: SocketAddr::from((REMOTE_IP, REMOTE_PORT)),
+            poll_delay: POLL_DELAY,
+            read_timeout: Some(READ_TIMEOUT),
+        }
+    }
+}
+
+/// whether the device is answering, as far as the backend can tell
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ConnectionState {
+    /// no socket connected, or shut down
+    #[default]
+    Disconnected,
+    /// connected, waiting for the first good frame
+    Connecting,
+    Connected,
+    /// polls stopped getting answers
+    Lost,
+}
+
+impl std::fmt::Display for ConnectionState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ConnectionState::Disconnected => "disconnected",
+            ConnectionState::Connecting => "connecting",
+            ConnectionState::Connected => "connected",
+            ConnectionState::Lost => "connection lost",
+        })
+    }
+}
+
+/// how the poll loop has been doing since the backend was created
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PollStats {
+    /// successful polls
+    pub polls: u32,
+    pub elapsed: Duration,
+    /// polls the device didn't answer within the read timeout
+    pub timeouts: u32,
+    /// polls that failed any other way, e.g. a malformed frame
+    pub failures: u32,
+    /// frames thrown away for arriving after a newer one
+    pub out_of_order: u32,
+    /// mean and standard deviation of the time between the latest samples
+    pub interval_mean: Duration,
+    pub interval_std_dev: Duration,
+    /// intervals well over the mean, see HITCH_FACTOR
+    pub hitches: u32,
+}
+
+impl PollStats {
+    /// successful polls per second
+    pub fn rate(&self) -> f64 {
+        self.polls as f64 / self.elapsed.as_secs_f64()
+    }
+
+    /// polls that didn't produce values
+    pub fn dropped(&self) -> u32 {
+        self.timeouts + self.failures + self.out_of_order
+    }
 }
 
+/// called with the new values whenever a poll changes them
+pub type ValuesChanged = dyn FnMut(&AnalogValues) + Send + Sync;
+
 pub struct ViewerBackend {
-    socket: UdpSocket,
+    transport: Box<dyn Transport>,
+    remote_addr: SocketAddr,
+    poll_delay: Duration,
     analog_vals: AnalogValues,
+    raw_vals: AnalogValues, // analog_vals as decoded, before the filter
+    channels: usize,        // expected per frame, grows if the device sends more
+    has_data: bool,         // a frame has been decoded, all-zero values are a valid reading
     last_poll: Instant,
+    intervals: VecDeque<Duration>, // between the last INTERVAL_WINDOW samples, oldest first
+    hitches: u32,
+    last_frame: Instant, // last valid frame, or when the link was last declared dead
+    frame_timeout: Option<Duration>, // None to keep retrying the same link forever
+    stall_timeout: Option<Duration>, // None to trust the device to wait for us however long
+    last_request: Option<Instant>, // last poll sent since the handshake
     polled_amt: u32,
+    timeouts: u32,
+    failures: u32,
+    out_of_order: u32,
+    failed_polls: u32,         // in a row, 0 while the device answers
+    retry_backoff: Duration,   // wait after the next failure
+    retry_at: Option<Instant>, // no polls before this after a failure
+    reconnect_pending: bool,   // the socket failed, reconnect before the next poll
     started: Instant,
+    integrals: [Option<f64>; MAX_CHANNELS], // None if the channel isn't being integrated
+    overrides: [Option<u16>; MAX_CHANNELS], // forced display values, see override_channel()
+    calibrations: [Calibration; MAX_CHANNELS],
+    vref_mv: f32, // what the channels on the ideal calibration assume
+    transforms: [DisplayTransform; MAX_CHANNELS],
+    thresholds: [Thresholds; MAX_CHANNELS],
+    extremes: [Option<(u16, u16)>; MAX_CHANNELS], // (min, max) since the last reset
+    alarms: [bool; MAX_CHANNELS],                 // outside their thresholds as of the last frame
+    enabled: [bool; MAX_CHANNELS], // disabled channels are still decoded, but skip alarms and peaks
+    filter_alphas: [f32; MAX_CHANNELS], // 1.0 passes values through unfiltered
+    filtered: [Option<f32>; MAX_CHANNELS], // EMA state, None until the first frame after a reconnect
+    auto_range_rate: Option<f32>,          // None if auto ranging is off
+    auto_ranges: [Option<AutoRange>; MAX_CHANNELS],
+    decoder: Box<dyn PacketDecoder>,
+    status_byte: bool,              // frames carry a status byte after the values
+    sequence_byte: bool,            // then a running sequence number
+    checksum_byte: bool,            // then a checksum of everything before it
+    last_sequence: Option<u8>,      // of the newest frame since the handshake
+    fragmentation_threshold: usize, // frames longer than this get a warning
+    device_flags: DeviceFlags,
+    value_revision: u64,
+    values_changed: Option<Box<ValuesChanged>>, // see on_values_changed()
+    recording: Option<CsvRecorder>,
+    recording_decimation: Decimation,
     initialized: bool,
+    init_attempts: u32,              // 0 if the device never acknowledges init
+    device_info: Option<DeviceInfo>, // from the last handshake, cleared when it reruns
+    known_device_info: Option<DeviceInfo>, // last info ever received, to notice firmware changes
+    connection_history: VecDeque<ConnectionEvent>, // the last CONNECTION_HISTORY_LEN events
+    awaiting_first_poll: bool,       // the next poll outcome goes into the connection history
+    requested_at: Option<Instant>,   // try_poll() sent a request and is waiting for its reply
+    command_acks: bool,              // send_command() waits for the device to acknowledge
+    connection_state: ConnectionState,
+    history: Vec<HistorySample>, // oldest first, may hold expired samples, see history()
+    history_window: Option<Duration>, // None if no history is kept
 }
 
 #[derive(Debug)]
 pub enum ViewerBackendError {
     SocketError(std::io::Error),
     ParserError(String), // reason
+    Truncated(usize),    // bytes received, the reply filled the whole buffer
+    Timeout(Duration),   // how long we waited for the device to reply
 }
 
 impl From<ViewerBackendError> for std::io::Error {
     fn from(e: ViewerBackendError) -> Self {
         match e {
             ViewerBackendError::SocketError(e) => e,
-            ViewerBackendError::ParserError(s) => std::io::Error::new(std::io::ErrorKind::Other, s),
+            ViewerBackendError::ParserError(s) => std::io::Error::other(s),
+            ViewerBackendError::Truncated(amt) => std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("reply truncated at {} bytes", amt),
+            ),
+            ViewerBackendError::Timeout(after) => std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                format!("no reply within {:?}", after),
+            ),
         }
     }
 }
@@ -50,116 +769,1227 @@ impl ViewerBackend {
     /// connect to the device so we can poll values
     /// the device expects us to poll it often, otherwise it needs to be restarted
     pub fn connect() -> Result<Self, ViewerBackendError> {
-        let socket = UdpSocket::bind(SocketAddr::from((LOCAL_IP, PORT)))
-            .map_err(|e| ViewerBackendError::SocketError(e))?;
+        Self::connect_with(ViewerBackendConfig::default())
+    }
+
+    /// find the device by broadcasting `discover` to `REMOTE_PORT`
+    /// the device answers with the `ip:port` to poll, or anything else to be polled where it answered from
+    /// replies are collected for the whole `timeout`, if several devices answer the first one wins
+    pub fn discover(timeout: Duration) -> Result<SocketAddr, ViewerBackendError> {
+        Self::discover_at(
+            SocketAddr::from((Ipv4Addr::BROADCAST, REMOTE_PORT)),
+            timeout,
+        )
+    }
+
+    fn discover_at(
+        target: SocketAddr,
+        timeout: Duration,
+    ) -> Result<SocketAddr, ViewerBackendError> {
+        let socket = UdpSocket::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)))
+            .map_err(ViewerBackendError::SocketError)?;
+        socket
+            .set_broadcast(true)
+            .map_err(ViewerBackendError::SocketError)?;
+        log::info!("discovering devices on {}", target);
+        socket
+            .send_to(b"discover", target)
+            .map_err(ViewerBackendError::SocketError)?;
 
-        Ok(ViewerBackend {
-            socket,
+        let deadline = Instant::now() + timeout;
+        let mut found = Vec::new();
+        let mut buf = [0u8; 64];
+        while let Some(left) = deadline
+            .checked_duration_since(Instant::now())
+            .filter(|left| !left.is_zero())
+        {
+            socket
+                .set_read_timeout(Some(left))
+                .map_err(ViewerBackendError::SocketError)?;
+            let (amt, from) = match socket.recv_from(&mut buf) {
+                Ok(received) => received,
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    break
+                }
+                Err(e) => Err(ViewerBackendError::SocketError(e))?,
+            };
+
+            let addr = std::str::from_utf8(&buf[..amt])
+                .ok()
+                .and_then(|reply| reply.trim().parse().ok())
+                .unwrap_or(from);
+            if !found.contains(&addr) {
+                log::info!("device at {} answered from {}", addr, from);
+                found.push(addr);
+            }
+        }
+
+        match found.as_slice() {
+            [] => Err(ViewerBackendError::Timeout(timeout)),
+            [addr] => Ok(*addr),
+            [addr, others @ ..] => {
+                log::warn!(
+                    "several devices answered, using {}, ignoring {:?}",
+                    addr,
+                    others
+                );
+                Ok(*addr)
+            }
+        }
+    }
+
+    /// same as connect(), but with the addresses and timings from `config`
+    /// either address may be IPv4 or IPv6, the socket is bound to the device's family
+    pub fn connect_with(config: ViewerBackendConfig) -> Result<Self, ViewerBackendError> {
+        let local_addr = bind_addr(config.local_addr, config.remote_addr);
+        let socket = UdpSocket::bind(local_addr).map_err(ViewerBackendError::SocketError)?;
+        socket
+            .set_read_timeout(config.read_timeout)
+            .map_err(ViewerBackendError::SocketError)?;
+
+        if local_addr.port() == 0 {
+            let local_addr = socket
+                .local_addr()
+                .map_err(ViewerBackendError::SocketError)?;
+            log::info!("bound to ephemeral port {}", local_addr.port());
+        }
+        let opened = match socket.local_addr() {
+            Ok(addr) => format!("udp socket on {}", addr),
+            Err(_) => String::from("udp socket"),
+        };
+
+        let mut backend = Self::with_transport(Box::new(socket), &config);
+        backend.record(ConnectionEventKind::Opened(opened));
+        Ok(backend)
+    }
+
+    /// read frames from a named pipe instead of polling a device over UDP
+    /// blocks until another process opens the pipe for writing
+    pub fn connect_fifo(path: &Path) -> Result<Self, ViewerBackendError> {
+        let fifo = FifoTransport::open(path).map_err(ViewerBackendError::SocketError)?;
+
+        let mut backend = Self::with_transport(Box::new(fifo), &ViewerBackendConfig::default());
+        backend.record(ConnectionEventKind::Opened(format!(
+            "fifo {}",
+            path.display()
+        )));
+        Ok(backend)
+    }
+
+    /// play back a recording made with start_recording(), through the same pipeline as live readings
+    /// the frames are plain binary with as many channels as the recording, whatever the device sends
+    pub fn replay(path: &Path, config: ReplayConfig) -> Result<Self, ViewerBackendError> {
+        let replay = CsvReplay::open(path, config).map_err(ViewerBackendError::SocketError)?;
+        let channels = replay.channels();
+
+        // the recording sets the pace, polls only wait for the next row
+        let mut backend = Self::with_transport(
+            Box::new(replay),
+            &ViewerBackendConfig {
+                poll_delay: Duration::ZERO,
+                ..ViewerBackendConfig::default()
+            },
+        );
+        backend.set_channels(channels);
+        backend.record(ConnectionEventKind::Opened(format!(
+            "replay of {}",
+            path.display()
+        )));
+        Ok(backend)
+    }
+
+    /// poll a simulated device instead of real hardware, for demos and UI work
+    pub fn simulate(config: SimulationConfig) -> Self {
+        let mut backend = Self::with_transport(
+            Box::new(SimulatedDevice::new(config)),
+            &ViewerBackendConfig::default(),
+        );
+        backend.record(ConnectionEventKind::Opened(String::from(
+            "simulated device",
+        )));
+        backend
+    }
+
+    fn with_transport(transport: Box<dyn Transport>, config: &ViewerBackendConfig) -> Self {
+        ViewerBackend {
+            transport,
+            remote_addr: config.remote_addr,
+            poll_delay: config.poll_delay,
             analog_vals: AnalogValues {
-                a0: 0,
-                a1: 0,
-                a2: 0,
-                a3: 0,
+                channels: Vec::new(),
+            },
+            raw_vals: AnalogValues {
+                channels: Vec::new(),
             },
+            channels: DEFAULT_CHANNELS,
+            has_data: false,
             last_poll: Instant::now(),
+            intervals: VecDeque::with_capacity(INTERVAL_WINDOW),
+            hitches: 0,
+            last_frame: Instant::now(),
+            frame_timeout: None,
+            stall_timeout: Some(STALL_TIMEOUT),
+            last_request: None,
             polled_amt: 0,
+            timeouts: 0,
+            failures: 0,
+            out_of_order: 0,
+            failed_polls: 0,
+            retry_backoff: RETRY_BACKOFF_MIN,
+            retry_at: None,
+            reconnect_pending: false,
             started: Instant::now(),
+            integrals: [None; MAX_CHANNELS],
+            overrides: [None; MAX_CHANNELS],
+            calibrations: [Calibration::default(); MAX_CHANNELS],
+            vref_mv: VREF_MV,
+            transforms: [DisplayTransform::default(); MAX_CHANNELS],
+            thresholds: [Thresholds::default(); MAX_CHANNELS],
+            extremes: [None; MAX_CHANNELS],
+            enabled: [true; MAX_CHANNELS],
+            alarms: [false; MAX_CHANNELS],
+            filter_alphas: [1.0; MAX_CHANNELS],
+            filtered: [None; MAX_CHANNELS],
+            auto_range_rate: None,
+            auto_ranges: [None; MAX_CHANNELS],
+            decoder: Box::new(BeU16Decoder),
+            status_byte: false,
+            sequence_byte: false,
+            checksum_byte: false,
+            last_sequence: None,
+            fragmentation_threshold: FRAGMENTATION_THRESHOLD,
+            device_flags: DeviceFlags::default(),
+            value_revision: 0,
+            values_changed: None,
+            recording: None,
+            recording_decimation: Decimation::default(),
             initialized: false,
-        })
+            init_attempts: INIT_ATTEMPTS,
+            device_info: None,
+            known_device_info: None,
+            connection_history: VecDeque::with_capacity(CONNECTION_HISTORY_LEN),
+            awaiting_first_poll: true,
+            requested_at: None,
+            command_acks: false,
+            connection_state: ConnectionState::default(),
+            history: Vec::new(),
+            history_window: Some(HISTORY_WINDOW),
+        }
+    }
+
+    /// remember a connection event for connection_history()
+    fn record(&mut self, kind: ConnectionEventKind) {
+        if self.connection_history.len() == CONNECTION_HISTORY_LEN {
+            self.connection_history.pop_front();
+        }
+        self.connection_history.push_back(ConnectionEvent {
+            at: SystemTime::now(),
+            kind,
+        });
+    }
+
+    /// the most recent connection, handshake and first poll events, oldest first
+    /// meant for troubleshooting a link that won't come up
+    pub fn connection_history(&self) -> Vec<ConnectionEvent> {
+        self.connection_history.iter().cloned().collect()
     }
 
     /// parse a piece of the message (split by :)
+    /// surrounding whitespace is ignored
     fn parse_piece(split: &mut Split<'_, char>, name: &str) -> Result<u16, ViewerBackendError> {
-        Ok(split
+        split
             .next()
             .ok_or(ViewerBackendError::ParserError(format!(
                 "missing {} value",
                 name
             )))?
+            .trim()
             .parse()
             .map_err(|e| {
                 ViewerBackendError::ParserError(format!("invalid {} value: {:?}", name, e))
-            })?)
+            })
+    }
+
+    /// check that the next piece of the message is the name of the value that follows it
+    fn expect_label(split: &mut Split<'_, char>, name: &str) -> Result<(), ViewerBackendError> {
+        match split.next().map(str::trim) {
+            Some(label) if label == name => Ok(()),
+            Some(label) => Err(ViewerBackendError::ParserError(format!(
+                "expected {} label, got {:?}",
+                name, label
+            ))),
+            None => Err(ViewerBackendError::ParserError(format!(
+                "missing {} label",
+                name
+            ))),
+        }
+    }
+
+    /// decode a text frame of the form `a0:<value>:a1:<value>:...` with `channels` values
+    /// tolerates surrounding whitespace, a line terminator and a trailing `:`
+    pub fn parse_text_frame(
+        frame: &str,
+        channels: usize,
+        extra: ExtraFields,
+    ) -> Result<AnalogValues, ViewerBackendError> {
+        let frame = frame.trim();
+        let frame = frame.strip_suffix(':').unwrap_or(frame);
+        let mut split = frame.split(':');
+
+        let mut values = vec![0u16; channels];
+        for (i, value) in values.iter_mut().enumerate() {
+            let name = format!("a{}", i);
+            Self::expect_label(&mut split, &name)?;
+            *value = Self::parse_piece(&mut split, &name)?;
+        }
+
+        let rest: Vec<&str> = split.collect();
+        if !rest.is_empty() && extra == ExtraFields::Reject {
+            Err(ViewerBackendError::ParserError(format!(
+                "unexpected trailing fields: {:?}",
+                rest.join(":")
+            )))?
+        }
+
+        Ok(AnalogValues { channels: values })
     }
 
     /// reads analog vals without updating them
     /// helpful if &mut self is not available
     pub fn read(&self) -> Result<&AnalogValues, ViewerBackendError> {
-        match self.analog_vals {
-            AnalogValues {
-                a0: 0,
-                a1: 0,
-                a2: 0,
-                a3: 0,
-            } => Err(ViewerBackendError::ParserError(String::from(
+        if !self.has_data {
+            Err(ViewerBackendError::ParserError(String::from(
                 "no values read yet",
-            )))?,
-            _ => Ok(&self.analog_vals),
+            )))?
         }
+        Ok(&self.analog_vals)
     }
 
-    pub fn connect_socket(&mut self) -> Result<(), ViewerBackendError> {
-        self.socket
-            .connect(SocketAddr::from((REMOTE_IP, PORT)))
-            .map_err(|e| ViewerBackendError::SocketError(e))?;
-        Ok(())
+    /// the last values as the device sent them, read() has them after the filter
+    pub fn read_raw(&self) -> Result<&AnalogValues, ViewerBackendError> {
+        self.read()?;
+        Ok(&self.raw_vals)
     }
 
-    /// poll new values or reads cached ones if delay has not yet elapsed
-    pub fn poll(&mut self) -> Result<&AnalogValues, ViewerBackendError> {
-        if self.last_poll.elapsed() < POLL_DELAY {
-            return Ok(&self.analog_vals);
+    /// how many channels each frame is expected to carry, at most `MAX_CHANNELS`
+    /// a device reporting its channel count in the handshake overrides this
+    pub fn set_channels(&mut self, channels: usize) {
+        if channels > MAX_CHANNELS {
+            log::warn!(
+                "{} channels requested, only {} are supported",
+                channels,
+                MAX_CHANNELS
+            );
         }
+        self.channels = channels.clamp(1, MAX_CHANNELS);
+    }
 
-        log::info!("polling");
+    /// start or stop accumulating `value * dt` for a channel
+    /// enabling an already integrated channel keeps its running total
+    pub fn set_integral_enabled(&mut self, channel: usize, enabled: bool) {
+        let integral = &mut self.integrals[channel];
+        match (enabled, &integral) {
+            (true, None) => *integral = Some(0.0),
+            (false, _) => *integral = None,
+            _ => {}
+        }
+    }
 
-        /*
-        if !self.initialized {
-            log::info!("initializing conn");
-            self.socket
-                .send_to(b"init", SocketAddr::from((REMOTE_IP, PORT)))
-                .map_err(|e| ViewerBackendError::SocketError(e))?;
-            self.initialized = true;
+    /// time integral of a channel in raw count-seconds since it was enabled or last reset
+    /// 0 if the channel isn't being integrated
+    pub fn integral(&self, channel: usize) -> f64 {
+        self.integrals[channel].unwrap_or(0.0)
+    }
+
+    /// restart a channel's integral from zero
+    pub fn reset_integral(&mut self, channel: usize) {
+        if let Some(integral) = &mut self.integrals[channel] {
+            *integral = 0.0;
         }
-        */
+    }
 
-        let mut buf = [0u8; 8];
+    /// force the displayed value of a channel, or go back to the live reading with `None`
+    /// debugging aid for demoing alarms and the display, read() still returns the live values
+    pub fn override_channel(&mut self, channel: usize, value: Option<u16>) {
+        if self.overrides[channel] != value {
+            log::warn!("channel a{} override: {:?}", channel, value);
+            self.value_revision += 1;
+        }
+        self.overrides[channel] = value;
+    }
 
-        self.socket
-            .send(b"poll")
-            .map_err(|e| ViewerBackendError::SocketError(e))?;
+    /// bumped whenever the decoded values, device flags or overrides change, or peaks are reset
+    /// compare against a previous revision to skip redrawing or exporting the same values
+    pub fn value_revision(&self) -> u64 {
+        self.value_revision
+    }
 
-        let amt = match self.socket.recv(&mut buf) {
-            Ok(amt) => amt,
+    /// call `callback` from poll() each time a frame bumps value_revision(), replacing any earlier one
+    /// it runs on the polling thread with the backend locked, so keep it short and never block in it
+    pub fn on_values_changed(&mut self, callback: Box<ValuesChanged>) {
+        self.values_changed = Some(callback);
+    }
+
+    /// append every successful poll to the CSV file at `path`, replacing any recording in progress
+    /// timestamps are milliseconds since the backend was created
+    pub fn start_recording(&mut self, path: &Path) -> Result<(), ViewerBackendError> {
+        self.stop_recording();
+
+        let recorder = CsvRecorder::create(path, self.channels, self.recording_decimation)
+            .map_err(ViewerBackendError::SocketError)?;
+        log::info!("recording to {}", path.display());
+        self.recording = Some(recorder);
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) {
+        if let Some(recorder) = self.recording.take() {
+            log::info!("recording stopped");
+            if let Err(e) = recorder.finish() {
+                log::error!("error finishing recording: {:?}", e);
+            }
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// how many polls make it into recordings started from now on
+    pub fn set_recording_decimation(&mut self, decimation: Decimation) {
+        self.recording_decimation = decimation;
+    }
+
+    /// fit each channel's display range to what it actually reads
+    /// `rate` is how fast per second the range narrows again after an extreme, `None` turns it off
+    pub fn set_auto_range(&mut self, rate: Option<f32>) {
+        self.auto_range_rate = rate;
+        if rate.is_none() {
+            self.auto_ranges = [None; MAX_CHANNELS];
+        }
+    }
+
+    /// raw (min, max) display range fitted to a channel, once it has been read
+    pub fn auto_range(&self, channel: usize) -> Option<(f32, f32)> {
+        self.auto_ranges[channel].map(|range| range.range())
+    }
+
+    /// value a channel is currently forced to, if any
+    pub fn channel_override(&self, channel: usize) -> Option<u16> {
+        self.overrides[channel]
+    }
+
+    pub fn set_calibration(&mut self, channel: usize, calibration: Calibration) {
+        self.calibrations[channel] = calibration;
+    }
+
+    pub fn calibration(&self, channel: usize) -> Calibration {
+        self.calibrations[channel]
+    }
+
+    /// the ADC reference in mV, for boards that don't run at the nominal one
+    /// channels on the ideal calibration for the old reference move to the new one, calibrated ones keep theirs
+    pub fn set_vref_mv(&mut self, vref_mv: f32) {
+        let old = Calibration::ideal(self.vref_mv);
+        for calibration in self.calibrations.iter_mut().filter(|c| **c == old) {
+            *calibration = Calibration::ideal(vref_mv);
+        }
+        self.vref_mv = vref_mv;
+    }
+
+    pub fn vref_mv(&self) -> f32 {
+        self.vref_mv
+    }
+
+    /// how a channel's calibrated value turns into what it shows, e.g. °C for the temperature sensor
+    pub fn set_transform(&mut self, channel: usize, transform: DisplayTransform) {
+        self.transforms[channel] = transform;
+    }
+
+    pub fn transform(&self, channel: usize) -> DisplayTransform {
+        self.transforms[channel]
+    }
+
+    /// band a channel's displayed value has to stay inside, checked against each frame from then on
+    pub fn set_thresholds(&mut self, channel: usize, thresholds: Thresholds) {
+        self.thresholds[channel] = thresholds;
+    }
+
+    /// a disabled channel is still decoded and recorded, but never alarms or holds peaks
+    /// e.g. for an unconnected input that only reads noise
+    pub fn set_channel_enabled(&mut self, channel: usize, enabled: bool) {
+        self.enabled[channel] = enabled;
+        if !enabled {
+            self.extremes[channel] = None;
+            self.alarms[channel] = false;
+        }
+        self.value_revision += 1;
+    }
+
+    pub fn channel_enabled(&self, channel: usize) -> bool {
+        self.enabled[channel]
+    }
+
+    /// lowest and highest unfiltered value of a channel since connecting or the last reset_extremes()
+    pub fn extremes(&self, channel: usize) -> Option<(u16, u16)> {
+        self.extremes[channel]
+    }
+
+    pub fn reset_extremes(&mut self) {
+        self.extremes = [None; MAX_CHANNELS];
+        self.value_revision += 1;
+    }
+
+    /// whether the channel was outside its thresholds in the last frame, overrides count
+    pub fn in_alarm(&self, channel: usize) -> bool {
+        self.alarms[channel]
+    }
+
+    /// smooth a channel with an exponential moving average before anything reads it
+    /// `alpha` is the weight of each new sample in [0, 1], 1.0 turns the filter off
+    pub fn set_filter_alpha(&mut self, channel: usize, alpha: f32) {
+        self.filter_alphas[channel] = alpha.clamp(0.0, 1.0);
+    }
+
+    pub fn filter_alpha(&self, channel: usize) -> f32 {
+        self.filter_alphas[channel]
+    }
+
+    /// how long decoded readings are kept for history(), `None` stops keeping them
+    pub fn set_history_window(&mut self, window: Option<Duration>) {
+        self.history_window = window;
+        if window.is_none() {
+            self.history.clear();
+        }
+    }
+
+    /// mean raw value of a channel over the last `window`, out of the history
+    /// so it reaches back no further than the history window, and is the current value without history
+    pub fn window_average(&self, channel: usize, window: Duration) -> f32 {
+        let current = self.analog_vals.channels.get(channel).copied().unwrap_or(0);
+        let samples = self.history();
+        let start = samples.partition_point(|(at, _)| at.elapsed() > window);
+        let values: Vec<u16> = samples[start..]
+            .iter()
+            .filter_map(|(_, values)| values.channels.get(channel).copied())
+            .collect();
+
+        if values.is_empty() {
+            return current as f32;
+        }
+        values.iter().map(|&value| value as f32).sum::<f32>() / values.len() as f32
+    }
+
+    /// every reading decoded within the history window, oldest first
+    /// as the device sent them, like recordings, the filter only applies to read()
+    pub fn history(&self) -> &[HistorySample] {
+        let Some(window) = self.history_window else {
+            return &[];
+        };
+        let start = self
+            .history
+            .partition_point(|(at, _)| at.elapsed() > window);
+        &self.history[start..]
+    }
+
+    /// the last read values in mV, each through its channel's calibration
+    /// read() still has the raw counts
+    pub fn read_calibrated(&self) -> Result<Vec<f32>, ViewerBackendError> {
+        Ok(self
+            .read()?
+            .iter()
+            .zip(&self.calibrations)
+            .map(|(&raw, calibration)| calibration.to_mv(raw as f32))
+            .collect())
+    }
+
+    /// the last read values as the channels show them, calibrated and then transformed
+    pub fn read_display(&self) -> Result<Vec<f32>, ViewerBackendError> {
+        Ok(self
+            .read_calibrated()?
+            .into_iter()
+            .zip(&self.transforms)
+            .map(|(calibrated, transform)| transform.apply(calibrated))
+            .collect())
+    }
+
+    /// how many times to send the init handshake before giving up
+    /// 0 sends init once without waiting for an ack, for firmware that never sends one
+    pub fn set_init_attempts(&mut self, attempts: u32) {
+        self.init_attempts = attempts;
+    }
+
+    /// send the init handshake until the device acknowledges it
+    /// a lost datagram would otherwise leave the device waiting while we think it's set up
+    /// the capabilities in the acknowledgement are (re)cached, the firmware may have changed
+    pub fn initialize(&mut self) -> Result<(), ViewerBackendError> {
+        self.device_info = None;
+        // the device starts counting again
+        self.last_sequence = None;
+
+        if !self.transport.handshakes() {
+            self.initialized = true;
+            return Ok(());
+        }
+        if self.init_attempts == 0 {
+            log::info!("initializing conn, not waiting for an ack");
+            self.transport
+                .send(b"init")
+                .map_err(ViewerBackendError::SocketError)?;
+            self.record(ConnectionEventKind::InitSent { attempt: 1 });
+            self.initialized = true;
+            return Ok(());
+        }
+
+        let read_timeout = self
+            .transport
+            .read_timeout()
+            .map_err(ViewerBackendError::SocketError)?;
+        self.transport
+            .set_read_timeout(Some(INIT_ACK_TIMEOUT))
+            .map_err(ViewerBackendError::SocketError)?;
+
+        // in a closure so the poll timeout is restored however the handshake ends
+        let mut handshake = || -> Result<Option<Vec<u8>>, ViewerBackendError> {
+            for attempt in 1..=self.init_attempts {
+                log::info!(
+                    "initializing conn (attempt {}/{})",
+                    attempt,
+                    self.init_attempts
+                );
+
+                self.transport
+                    .send(b"init")
+                    .map_err(ViewerBackendError::SocketError)?;
+                self.record(ConnectionEventKind::InitSent { attempt });
+
+                let mut buf = [0u8; 16];
+                match self.transport.recv(&mut buf, b"ack".len()) {
+                    Ok(amt) if buf[..amt].starts_with(b"ack") => {
+                        return Ok(Some(buf[..amt].to_vec()))
+                    }
+                    Ok(amt) => log::warn!("unexpected reply to init: {:?}", &buf[..amt]),
+                    Err(e) => log::warn!("init not acknowledged: {:?}", e),
+                }
+
+                thread::sleep(INIT_RETRY_DELAY);
+            }
+            Ok(None)
+        };
+        let ack = handshake();
+
+        self.transport
+            .set_read_timeout(read_timeout)
+            .map_err(ViewerBackendError::SocketError)?;
+        let ack = ack?;
+
+        let ack = match ack {
+            Some(ack) => ack,
+            None => {
+                let reason = format!(
+                    "device did not acknowledge init after {} attempts",
+                    self.init_attempts
+                );
+                self.record(ConnectionEventKind::InitFailed(reason));
+                Err(ViewerBackendError::Timeout(
+                    INIT_ACK_TIMEOUT * self.init_attempts,
+                ))?
+            }
+        };
+
+        log::info!("init acknowledged");
+        self.record(ConnectionEventKind::InitAcked);
+        self.initialized = true;
+        self.awaiting_first_poll = true;
+
+        self.device_info = DeviceInfo::from_ack(&ack)?;
+        if let Some(info) = &self.device_info {
+            log::info!("device info: {:?}", info);
+
+            if let Some(previous) = &self.known_device_info {
+                if previous != info {
+                    log::warn!(
+                        "device info changed from {:?} to {:?}, was the firmware updated?",
+                        previous,
+                        info
+                    );
+                }
+            }
+            self.known_device_info = Some(info.clone());
+            self.status_byte = info.status_byte;
+            self.set_channels(info.channels as usize);
+        }
+        self.check_frame_size();
+
+        Ok(())
+    }
+
+    /// how long polls may go without a valid frame before the link counts as dead and the handshake reruns
+    /// spans any number of failed receives, so it only fires if the read timeout lets them fail
+    pub fn set_frame_timeout(&mut self, timeout: Option<Duration>) {
+        self.frame_timeout = timeout;
+    }
+
+    /// how long the device waits between polls before it stops answering until initialized again
+    /// a longer gap, like a stalled poll thread, reruns the handshake before the next poll
+    /// gaps while failed polls back off don't count, the frame timeout is for those
+    pub fn set_stall_timeout(&mut self, timeout: Option<Duration>) {
+        self.stall_timeout = timeout;
+    }
+
+    /// capabilities reported by the device during the last handshake, if it sent any
+    pub fn device_info(&self) -> Option<&DeviceInfo> {
+        self.device_info.as_ref()
+    }
+
+    /// whether the device appends a status byte to each frame
+    /// overridden by the device info if the device reports it during the handshake
+    pub fn set_status_byte(&mut self, enabled: bool) {
+        self.status_byte = enabled;
+    }
+
+    /// how the values in replies are decoded, the frame options below only apply to binary decoders
+    /// for wire formats of custom firmware, the built-in ones are set with set_protocol()
+    pub fn set_decoder(&mut self, decoder: Box<dyn PacketDecoder>) {
+        self.decoder = decoder;
+    }
+
+    /// use one of the built-in decoders, `extra` is what the text one does with extra fields
+    pub fn set_protocol(&mut self, protocol: ProtocolMode, extra: ExtraFields) {
+        self.set_decoder(protocol.decoder(extra));
+    }
+
+    /// whether the device numbers its frames, so late ones can be dropped
+    /// the count wraps at 256, a frame up to 128 behind the newest one counts as late
+    pub fn set_sequence_byte(&mut self, enabled: bool) {
+        self.sequence_byte = enabled;
+    }
+
+    /// whether the device ends each frame with the wrapping sum of all its other bytes
+    pub fn set_checksum_byte(&mut self, enabled: bool) {
+        self.checksum_byte = enabled;
+    }
+
+    /// bytes after the values, each of them optional and only in binary frames
+    fn trailer_len(&self) -> usize {
+        if !self.decoder.binary() {
+            return 0;
+        }
+        self.status_byte as usize + self.sequence_byte as usize + self.checksum_byte as usize
+    }
+
+    /// bytes in one frame as the frame spec currently stands
+    /// the values as the decoder packs them, then the status byte, sequence number and checksum if enabled
+    /// with the stock decoder four channels make the legacy 8 byte frame, 9 with a sequence number and 10 with a checksum too
+    pub fn frame_len(&self) -> usize {
+        self.decoder.values_len(self.channels) + self.trailer_len()
+    }
+
+    pub fn set_fragmentation_threshold(&mut self, bytes: usize) {
+        self.fragmentation_threshold = bytes;
+    }
+
+    /// warn if frames are long enough that IP may fragment them, fragments get lost far more often
+    /// returns whether the frame size is within the threshold
+    pub fn check_frame_size(&mut self) -> bool {
+        let frame_len = self.frame_len();
+        let threshold = self.fragmentation_threshold;
+        if frame_len <= threshold {
+            return true;
+        }
+
+        log::warn!(
+            "{} byte frames exceed the {} byte fragmentation threshold, expect drops on lossy links",
+            frame_len,
+            threshold
+        );
+        self.record(ConnectionEventKind::FragmentationRisk {
+            frame_len,
+            threshold,
+        });
+        false
+    }
+
+    pub fn status_byte(&self) -> bool {
+        self.status_byte
+    }
+
+    /// flags from the last frame's status byte, all clear if status bytes are disabled
+    pub fn device_flags(&self) -> DeviceFlags {
+        self.device_flags
+    }
+
+    /// average successful polls per second since connecting
+    pub fn stats(&self) -> PollStats {
+        let (interval_mean, interval_std_dev) = self.interval_stats();
+        PollStats {
+            polls: self.polled_amt,
+            elapsed: self.started.elapsed(),
+            timeouts: self.timeouts,
+            failures: self.failures,
+            out_of_order: self.out_of_order,
+            interval_mean,
+            interval_std_dev,
+            hitches: self.hitches,
+        }
+    }
+
+    /// mean and standard deviation of the intervals between the latest samples, zero before the second
+    fn interval_stats(&self) -> (Duration, Duration) {
+        if self.intervals.is_empty() {
+            return (Duration::ZERO, Duration::ZERO);
+        }
+        let count = self.intervals.len() as f64;
+        let mean = self
+            .intervals
+            .iter()
+            .map(Duration::as_secs_f64)
+            .sum::<f64>()
+            / count;
+        let variance = self
+            .intervals
+            .iter()
+            .map(|interval| (interval.as_secs_f64() - mean).powi(2))
+            .sum::<f64>()
+            / count;
+        (
+            Duration::from_secs_f64(mean),
+            Duration::from_secs_f64(variance.sqrt()),
+        )
+    }
+
+    /// keep the time since the last sample for the interval stats, counting it if it's a hitch
+    fn record_interval(&mut self, interval: Duration) {
+        let (mean, _) = self.interval_stats();
+        // a handful of samples is too few to tell a hitch from the usual spread
+        if self.intervals.len() >= 10 && interval.as_secs_f64() > mean.as_secs_f64() * HITCH_FACTOR
+        {
+            log::info!(
+                "hitch, {:?} since the last sample, usually {:?}",
+                interval,
+                mean
+            );
+            self.hitches += 1;
+        }
+
+        if self.intervals.len() == INTERVAL_WINDOW {
+            self.intervals.pop_front();
+        }
+        self.intervals.push_back(interval);
+    }
+
+    pub fn connect_socket(&mut self) -> Result<(), ViewerBackendError> {
+        let remote_addr = self.remote_addr;
+        match self.transport.connect(remote_addr) {
+            Ok(()) => self.record(ConnectionEventKind::Connected(remote_addr)),
             Err(e) => {
-                // self.socket
-                //    .connect(SocketAddr::from((REMOTE_IP, PORT)))
-                //    .map_err(|e| ViewerBackendError::SocketError(e))?;
+                self.record(ConnectionEventKind::ConnectFailed(e.to_string()));
+                self.set_connection_state(ConnectionState::Disconnected);
+                Err(ViewerBackendError::SocketError(e))?
+            }
+        }
+        self.awaiting_first_poll = true;
+        self.set_connection_state(ConnectionState::Connecting);
+        Ok(())
+    }
 
-                Err(ViewerBackendError::SocketError(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    e.to_string(),
-                )))?
+    pub fn connection_state(&self) -> ConnectionState {
+        self.connection_state
+    }
+
+    fn set_connection_state(&mut self, state: ConnectionState) {
+        if state != self.connection_state {
+            log::info!("{} -> {}", self.connection_state, state);
+            self.connection_state = state;
+        }
+    }
+
+    /// whether send_command() waits for the device to answer `ack`, for firmware that confirms commands
+    pub fn set_command_acks(&mut self, enabled: bool) {
+        self.command_acks = enabled;
+    }
+
+    /// send a configuration command like `gain:2` to the device over the polling socket
+    /// waits up to the init acknowledgement timeout for an `ack` if set_command_acks() is on
+    /// errors without sending anything before connect_socket() or after shutdown()
+    pub fn send_command(&mut self, cmd: &str) -> Result<(), ViewerBackendError> {
+        if self.connection_state == ConnectionState::Disconnected {
+            Err(ViewerBackendError::ParserError(format!(
+                "can't send {:?}, not connected to the device yet",
+                cmd
+            )))?
+        }
+        if cmd.is_empty() || !cmd.is_ascii() {
+            Err(ViewerBackendError::ParserError(format!(
+                "command {:?} is not plain ASCII",
+                cmd
+            )))?
+        }
+        // its reply would be taken for the acknowledgement
+        if self.requested_at.is_some() {
+            Err(ViewerBackendError::ParserError(format!(
+                "can't send {:?} while a poll is waiting for its reply",
+                cmd
+            )))?
+        }
+
+        log::info!("sending {:?} to {}", cmd, self.remote_addr);
+        self.transport
+            .send(cmd.as_bytes())
+            .map_err(ViewerBackendError::SocketError)?;
+        if !self.command_acks {
+            return Ok(());
+        }
+
+        let read_timeout = self
+            .transport
+            .read_timeout()
+            .map_err(ViewerBackendError::SocketError)?;
+        self.transport
+            .set_read_timeout(Some(INIT_ACK_TIMEOUT))
+            .map_err(ViewerBackendError::SocketError)?;
+        let mut buf = [0u8; 16];
+        let received = self.transport.recv(&mut buf, b"ack".len());
+        self.transport
+            .set_read_timeout(read_timeout)
+            .map_err(ViewerBackendError::SocketError)?;
+
+        match received {
+            Ok(amt) if buf[..amt].starts_with(b"ack") => Ok(()),
+            Ok(amt) => Err(ViewerBackendError::ParserError(format!(
+                "unexpected reply to {:?}: {:?}",
+                cmd,
+                &buf[..amt]
+            ))),
+            Err(_) => Err(ViewerBackendError::Timeout(INIT_ACK_TIMEOUT)),
+        }
+    }
+
+    /// tell the device to stop sampling and finish any recording, before the viewer exits
+    /// polling again afterwards redoes the init handshake
+    pub fn shutdown(&mut self) -> Result<(), ViewerBackendError> {
+        self.stop_recording();
+        self.initialized = false;
+        self.set_connection_state(ConnectionState::Disconnected);
+        log::info!("sending stop to {}", self.remote_addr);
+        self.transport
+            .send(b"stop")
+            .map_err(ViewerBackendError::SocketError)
+    }
+
+    /// reconnect to the device and redo the init handshake on the next poll
+    /// for recovering a dropped link without restarting the viewer
+    pub fn reconnect(&mut self) -> Result<(), ViewerBackendError> {
+        log::info!("reconnecting to {}", self.remote_addr);
+        self.initialized = false;
+        self.last_frame = Instant::now();
+        self.filtered = [None; MAX_CHANNELS];
+        self.retry_backoff = RETRY_BACKOFF_MIN;
+        self.retry_at = None;
+        self.last_sequence = None;
+        self.requested_at = None;
+        self.reset_extremes();
+        self.connect_socket()
+    }
+
+    /// minimum time between polls, takes effect from the next poll
+    pub fn set_poll_delay(&mut self, delay: Duration) {
+        self.poll_delay = delay;
+    }
+
+    pub fn poll_delay(&self) -> Duration {
+        self.poll_delay
+    }
+
+    /// how long until the poll delay, or the backoff after a failure, allows the next request
+    pub fn next_poll_in(&self) -> Duration {
+        let delay = self.poll_delay.saturating_sub(self.last_poll.elapsed());
+        let backoff = self.retry_at.map_or(Duration::ZERO, |at| {
+            at.saturating_duration_since(Instant::now())
+        });
+        delay.max(backoff)
+    }
+
+    /// poll new values or reads cached ones if delay has not yet elapsed
+    pub fn poll(&mut self) -> Result<&AnalogValues, ViewerBackendError> {
+        if !self.next_poll_in().is_zero() {
+            return Ok(&self.analog_vals);
+        }
+
+        self.requested_at = None;
+        let result = self.send_poll().and_then(|_| self.receive_frame());
+        self.finish_poll(result).map(|_| &self.analog_vals)
+    }
+
+    /// poll() for callers with an event loop of their own, it never waits for the device to reply
+    /// sends the request once a poll is due, then returns `Ok(None)` until the reply is there
+    /// a reply missing for longer than the read timeout fails like it does in poll()
+    /// the init handshake still blocks, and so does the read on sources that can't be read without blocking
+    pub fn try_poll(&mut self) -> Result<Option<&AnalogValues>, ViewerBackendError> {
+        let requested_at = match self.requested_at {
+            Some(at) => at,
+            None if !self.next_poll_in().is_zero() => return Ok(None),
+            None => {
+                let sent = self.send_poll();
+                if sent.is_err() {
+                    return self.finish_poll(sent).map(|_| None);
+                }
+                *self.requested_at.insert(Instant::now())
             }
         };
-        log::info!("amt: {}", amt);
 
-        let mut values: [u16; 4] = [0, 0, 0, 0];
+        let timeout = self.transport.read_timeout().ok().flatten();
+        let received = match self.transport.set_nonblocking(true) {
+            Ok(()) => {
+                let received = self.receive_frame();
+                self.transport
+                    .set_nonblocking(false)
+                    .map_err(ViewerBackendError::SocketError)
+                    .and(received)
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::Unsupported => self.receive_frame(),
+            Err(e) => Err(ViewerBackendError::SocketError(e)),
+        };
+
+        // with nothing to read receive_frame() reports a timeout right away, it's only real past the read timeout
+        if matches!(received, Err(ViewerBackendError::Timeout(_)))
+            && timeout.is_none_or(|timeout| requested_at.elapsed() < timeout)
+        {
+            return Ok(None);
+        }
+        self.requested_at = None;
+        self.finish_poll(received).map(|_| Some(&self.analog_vals))
+    }
+
+    /// count a poll's outcome in the stats, connection state, backoff and connection history
+    fn finish_poll(
+        &mut self,
+        result: Result<(), ViewerBackendError>,
+    ) -> Result<(), ViewerBackendError> {
+        match &result {
+            Ok(()) => self.set_connection_state(ConnectionState::Connected),
+            Err(ViewerBackendError::Timeout(_)) => {
+                self.timeouts += 1;
+                self.set_connection_state(ConnectionState::Lost);
+            }
+            Err(ViewerBackendError::SocketError(_)) => {
+                self.failures += 1;
+                self.set_connection_state(ConnectionState::Lost);
+            }
+            // the device is answering, just not well
+            Err(_) => self.failures += 1,
+        }
+        self.back_off(&result);
+
+        match (&result, self.frame_timeout) {
+            (Ok(()), _) => self.last_frame = Instant::now(),
+            (Err(_), Some(timeout)) if self.last_frame.elapsed() >= timeout => {
+                log::warn!("no valid frame for {:?}, reinitializing", timeout);
+                self.record(ConnectionEventKind::FrameTimeout(self.last_frame.elapsed()));
+                self.initialized = false;
+                self.awaiting_first_poll = true;
+                self.last_frame = Instant::now();
+            }
+            (Err(_), _) => {}
+        }
+
+        if self.awaiting_first_poll {
+            self.awaiting_first_poll = false;
+            match &result {
+                Ok(()) => self.record(ConnectionEventKind::FirstPollOk),
+                Err(e) => self.record(ConnectionEventKind::FirstPollFailed(format!("{:?}", e))),
+            }
+        }
+
+        result
+    }
 
-        let mut offs = 0;
-        for i in 0..4 {
-            values[i] = u16::from_be_bytes([buf[offs], buf[offs + 1]]);
-            offs += 2;
+    /// space out polls while they keep failing, so a dead device isn't hammered with requests
+    fn back_off(&mut self, result: &Result<(), ViewerBackendError>) {
+        match result {
+            Ok(()) => {
+                if self.failed_polls > 0 {
+                    log::info!(
+                        "device answered again after {} failed polls",
+                        self.failed_polls
+                    );
+                }
+                self.failed_polls = 0;
+                self.retry_backoff = RETRY_BACKOFF_MIN;
+                self.retry_at = None;
+            }
+            Err(e) => {
+                self.failed_polls += 1;
+                self.reconnect_pending |= matches!(e, ViewerBackendError::SocketError(_));
+                log::info!("poll failed, retrying in {:?}", self.retry_backoff);
+                self.retry_at = Some(Instant::now() + self.retry_backoff);
+                self.retry_backoff = (self.retry_backoff * 2).min(RETRY_BACKOFF_MAX);
+            }
         }
+    }
+
+    /// ask the device for a frame, reconnecting and rerunning the handshake first if needed
+    fn send_poll(&mut self) -> Result<(), ViewerBackendError> {
+        log::info!("polling");
+
+        if self.reconnect_pending {
+            self.connect_socket()?;
+            self.reconnect_pending = false;
+        }
+
+        let gap = self.last_request.map(|at| at.elapsed());
+        if let (Some(gap), Some(timeout)) = (gap, self.stall_timeout) {
+            // without acks, init is still sent again and the device picks it up if it's listening
+            if self.initialized && self.failed_polls == 0 && gap >= timeout {
+                log::warn!(
+                    "no poll sent for {:?}, the device has likely stopped waiting, reinitializing",
+                    gap
+                );
+                self.record(ConnectionEventKind::PollGap(gap));
+                self.initialized = false;
+                self.awaiting_first_poll = true;
+            }
+        }
+
+        if !self.initialized {
+            self.initialize()?;
+        }
+
+        self.last_request = Some(Instant::now());
+        self.transport
+            .send(b"poll")
+            .map_err(ViewerBackendError::SocketError)
+    }
+
+    /// receive and decode the frame answering send_poll()
+    fn receive_frame(&mut self) -> Result<(), ViewerBackendError> {
+        let frame_len = self.frame_len();
+        // one spare byte, a datagram that fills it was longer than any frame and got cut off
+        let max_frame_len = self.decoder.max_frame_len()
+            + if self.decoder.binary() {
+                MAX_TRAILER_LEN
+            } else {
+                0
+            };
+        let mut buf = vec![0u8; max_frame_len + 1];
+        let buf = &mut buf[..];
 
-        self.analog_vals = AnalogValues {
-            a0: values[0],
-            a1: values[1],
-            a2: values[2],
-            a3: values[3],
+        let amt = match self.transport.recv(buf, frame_len) {
+            Ok(amt) => amt,
+            // unix reports an expired read timeout as WouldBlock, windows as TimedOut
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    std::io::ErrorKind::WouldBlock | std::io::ErrorKind::TimedOut
+                ) =>
+            {
+                let waited = self.transport.read_timeout().ok().flatten();
+                Err(ViewerBackendError::Timeout(waited.unwrap_or_default()))?
+            }
+            Err(e) => Err(ViewerBackendError::SocketError(e))?,
+        };
+        log::info!("amt: {}", amt);
+
+        if amt == buf.len() {
+            Err(ViewerBackendError::Truncated(amt))?
+        }
+        // whatever is left in the buffer past `amt` is stale, never decode it
+        let frame = &buf[..amt];
+        let decoded = if self.decoder.binary() {
+            self.decode_binary_frame(frame)?
+        } else {
+            let values = self.decoder.decode(frame, self.channels)?;
+            Some((values, self.device_flags))
         };
+        let Some((mut analog_vals, device_flags)) = decoded else {
+            return Ok(()); // late, dropped
+        };
+        // before filtering, a short spike is what peak hold is for
+        let tracked = self.extremes.iter_mut().zip(&self.enabled);
+        for ((extremes, &enabled), &value) in tracked.zip(&analog_vals) {
+            if !enabled {
+                continue;
+            }
+            let (min, max) = extremes.get_or_insert((value, value));
+            *min = (*min).min(value);
+            *max = (*max).max(value);
+        }
+        self.raw_vals = analog_vals.clone();
+        self.filter(&mut analog_vals);
+
+        let changed = analog_vals != self.analog_vals || device_flags != self.device_flags;
+        self.analog_vals = analog_vals;
+        self.device_flags = device_flags;
+        self.has_data = true;
+
+        if changed {
+            self.value_revision += 1;
+            if let Some(callback) = &mut self.values_changed {
+                callback(&self.analog_vals);
+            }
+        }
+
+        self.check_alarms();
+        if self.polled_amt > 0 {
+            self.record_interval(self.last_poll.elapsed());
+        }
 
-        log::info!("analog_vals: {:?}", self.analog_vals);
+        let values = &self.analog_vals.channels;
+
+        // the first sample has no previous one to measure dt from
+        if self.polled_amt > 0 {
+            let dt = self.last_poll.elapsed().as_secs_f64();
+            let integrated = self.integrals.iter_mut().zip(&self.enabled);
+            for ((integral, &enabled), &value) in integrated.zip(values) {
+                if let (Some(integral), true) = (integral, enabled) {
+                    *integral += value as f64 * dt;
+                }
+            }
+
+            if let Some(rate) = self.auto_range_rate {
+                for (range, &value) in self.auto_ranges.iter_mut().zip(values) {
+                    match range {
+                        Some(range) => range.update(value as f32, rate, dt as f32),
+                        None => *range = Some(AutoRange::new(value as f32)),
+                    }
+                }
+            }
+        }
+
+        log::info!(
+            "analog_vals: {:?}, filtered {:?}",
+            self.raw_vals,
+            self.analog_vals
+        );
+
+        if let Some(window) = self.history_window {
+            let now = Instant::now();
+            // evicting in batches keeps polls cheap, history() skips the leftovers
+            if self
+                .history
+                .first()
+                .is_some_and(|(oldest, _)| now.duration_since(*oldest) > window * 2)
+            {
+                let expired = self
+                    .history
+                    .partition_point(|(at, _)| now.duration_since(*at) > window);
+                self.history.drain(..expired);
+            }
+            self.history.push((now, self.raw_vals.clone()));
+        }
+
+        if let Some(recorder) = &mut self.recording {
+            let timestamp_ms = self.started.elapsed().as_millis();
+            if let Err(e) = recorder.record(Instant::now(), timestamp_ms, &self.raw_vals.channels) {
+                log::error!("error recording, stopping: {:?}", e);
+                self.recording = None;
+            }
+        }
 
         self.last_poll = Instant::now();
         self.polled_amt += 1;
@@ -167,7 +1997,110 @@ impl ViewerBackend {
         log::info!("polled {} times", self.polled_amt);
         log::info!("started {} ms ago", self.started.elapsed().as_millis());
 
-        Ok(&self.analog_vals)
+        Ok(())
+    }
+
+    /// check and decode a binary frame, `None` if it arrived too late to use
+    fn decode_binary_frame(
+        &mut self,
+        frame: &[u8],
+    ) -> Result<Option<(AnalogValues, DeviceFlags)>, ViewerBackendError> {
+        if self.checksum_byte {
+            let (checksum, rest) = frame
+                .split_last()
+                .ok_or(ViewerBackendError::ParserError(String::from("empty frame")))?;
+            let expected = rest.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte));
+            if *checksum != expected {
+                Err(ViewerBackendError::ParserError(format!(
+                    "checksum mismatch, got {:#04x}, expected {:#04x}",
+                    checksum, expected
+                )))?
+            }
+        }
+
+        let amt = frame.len();
+        if amt < self.trailer_len() {
+            Err(ViewerBackendError::ParserError(format!(
+                "missing status, sequence or checksum byte, got {} bytes",
+                amt
+            )))?
+        }
+        let values_len = amt - self.trailer_len();
+        let mut device_flags = self.device_flags;
+        if self.status_byte {
+            device_flags = DeviceFlags::from_bits(frame[values_len]);
+        }
+
+        if self.sequence_byte {
+            let sequence = frame[values_len + self.status_byte as usize];
+            if let Some(last) = self.last_sequence {
+                if sequence.wrapping_sub(last) as i8 <= 0 {
+                    log::info!("dropping frame {}, it arrived after {}", sequence, last);
+                    self.out_of_order += 1;
+                    return Ok(None);
+                }
+            }
+            self.last_sequence = Some(sequence);
+        }
+
+        let analog_vals = self.decoder.decode(&frame[..values_len], self.channels)?;
+        if analog_vals.len() > MAX_CHANNELS {
+            Err(ViewerBackendError::ParserError(format!(
+                "frame carries {} channels, only {} are supported",
+                analog_vals.len(),
+                MAX_CHANNELS
+            )))?
+        }
+        if analog_vals.len() > self.channels {
+            log::info!(
+                "device sent {} channels, expected {}",
+                analog_vals.len(),
+                self.channels
+            );
+            self.channels = analog_vals.len();
+        }
+
+        Ok(Some((analog_vals, device_flags)))
+    }
+
+    /// compare each channel against its thresholds, logging when it leaves or re-enters its band
+    fn check_alarms(&mut self) {
+        for (channel, &value) in self.analog_vals.iter().enumerate() {
+            if !self.enabled[channel] {
+                continue;
+            }
+            let value = self.overrides[channel].unwrap_or(value);
+            let calibrated = self.calibrations[channel].to_mv(value as f32);
+            let shown = self.transforms[channel].apply(calibrated);
+            let alarm = self.thresholds[channel].exceeded(shown);
+
+            if alarm != self.alarms[channel] {
+                if alarm {
+                    log::warn!(
+                        "a{} in alarm: {} outside {:?}",
+                        channel,
+                        shown,
+                        self.thresholds[channel]
+                    );
+                } else {
+                    log::info!("a{} back within its thresholds", channel);
+                }
+                self.alarms[channel] = alarm;
+            }
+        }
+    }
+
+    /// run freshly decoded values through each channel's EMA filter
+    fn filter(&mut self, analog_vals: &mut AnalogValues) {
+        let channels = self.filtered.iter_mut().zip(&self.filter_alphas);
+        for (value, (filtered, &alpha)) in analog_vals.channels.iter_mut().zip(channels) {
+            let next = match *filtered {
+                Some(previous) if alpha < 1.0 => previous + (*value as f32 - previous) * alpha,
+                _ => *value as f32,
+            };
+            *filtered = Some(next);
+            *value = next.round() as u16;
+        }
     }
 }
 
@@ -182,11 +2115,21 @@ impl std::fmt::Display for ViewerBackend {
             }
         };
 
-        write!(
-            f,
-            "(a0: {}, a1: {}, a2: {}, a3: {})",
-            vals.a0, vals.a1, vals.a2, vals.a3
-        )
+        write!(f, "{}", vals)
+    }
+}
+
+/// e.g. `(a0: 1024, a1: 2048, a2: 0, a3: 4095)`
+impl std::fmt::Display for AnalogValues {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "(")?;
+        for (channel, value) in self.iter().enumerate() {
+            if channel > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "a{}: {}", channel, value)?;
+        }
+        write!(f, ")")
     }
 }
 
@@ -196,3 +2139,962 @@ impl std::fmt::Debug for ViewerBackend {
         (self as &dyn std::fmt::Display).fmt(f)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emulator::{EmulatedDevice, FrameLayout};
+
+    #[test]
+    fn be_u16_decoder_reads_each_channel_at_its_offset() {
+        let buf = [0x01, 0x00, 0x02, 0x00, 0x03, 0x00, 0x04, 0x00];
+
+        assert_eq!(
+            BeU16Decoder.decode(&buf, 4).unwrap(),
+            AnalogValues {
+                channels: vec![256, 512, 768, 1024],
+            }
+        );
+    }
+
+    #[test]
+    fn be_u16_decoder_handles_boundary_values() {
+        let buf = [0x00, 0x00, 0xFF, 0xFF, 0x12, 0x34, 0x00, 0x01];
+
+        assert_eq!(
+            BeU16Decoder.decode(&buf, 4).unwrap().channels,
+            [0x0000, 0xFFFF, 0x1234, 0x0001]
+        );
+    }
+
+    #[test]
+    fn be_u16_decoder_rejects_short_buffer() {
+        assert!(matches!(
+            BeU16Decoder.decode(&[0x01, 0x00, 0x02, 0x00, 0x03, 0x00], 4),
+            Err(ViewerBackendError::ParserError(_))
+        ));
+        assert!(matches!(
+            BeU16Decoder.decode(&[], 1),
+            Err(ViewerBackendError::ParserError(_))
+        ));
+    }
+
+    fn text_frame_values() -> AnalogValues {
+        AnalogValues {
+            channels: vec![1234, 0, 4095, 17],
+        }
+    }
+
+    #[test]
+    fn parse_text_frame_accepts_line_terminator() {
+        let frame = "a0:1234:a1:0:a2:4095:a3:17\r\n";
+
+        assert_eq!(
+            ViewerBackend::parse_text_frame(frame, 4, ExtraFields::Reject).unwrap(),
+            text_frame_values()
+        );
+    }
+
+    #[test]
+    fn parse_text_frame_trims_spaces_and_trailing_separator() {
+        let frame = "  a0: 1234 :a1:0 : a2:4095:a3:17:  ";
+
+        assert_eq!(
+            ViewerBackend::parse_text_frame(frame, 4, ExtraFields::Reject).unwrap(),
+            text_frame_values()
+        );
+    }
+
+    #[test]
+    fn parse_text_frame_extra_fields_follow_policy() {
+        let frame = "a0:1234:a1:0:a2:4095:a3:17:a4:99\n";
+
+        assert_eq!(
+            ViewerBackend::parse_text_frame(frame, 4, ExtraFields::Ignore).unwrap(),
+            text_frame_values()
+        );
+        assert!(matches!(
+            ViewerBackend::parse_text_frame(frame, 4, ExtraFields::Reject),
+            Err(ViewerBackendError::ParserError(_))
+        ));
+    }
+
+    #[test]
+    fn parse_text_frame_rejects_missing_channel() {
+        assert!(matches!(
+            ViewerBackend::parse_text_frame("a0:1:a1:2:a2:3\n", 4, ExtraFields::Ignore),
+            Err(ViewerBackendError::ParserError(_))
+        ));
+    }
+
+    /// a backend talking to `device` over loopback
+    fn emulated_backend(device: &EmulatedDevice) -> ViewerBackend {
+        emulated_backend_with(device, ViewerBackendConfig::default())
+    }
+
+    /// the same with the rest of `config`, e.g. a shorter read timeout
+    /// the local address is always IPv4 loopback, the backend has to swap it for an IPv6 device
+    fn emulated_backend_with(
+        device: &EmulatedDevice,
+        config: ViewerBackendConfig,
+    ) -> ViewerBackend {
+        let mut backend = ViewerBackend::connect_with(ViewerBackendConfig {
+            local_addr: SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+            remote_addr: device.addr(),
+            ..config
+        })
+        .unwrap();
+        backend.connect_socket().unwrap();
+        backend
+    }
+
+    fn poll_after_delay(backend: &mut ViewerBackend) -> AnalogValues {
+        thread::sleep(POLL_DELAY * 2);
+        backend.poll().unwrap().clone()
+    }
+
+    #[test]
+    fn poll_reads_emulated_frame() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        device.set_values(&[1, 256, 4095, 0]);
+        let mut backend = emulated_backend(&device);
+
+        assert_eq!(
+            poll_after_delay(&mut backend),
+            AnalogValues {
+                channels: vec![1, 256, 4095, 0],
+            }
+        );
+        assert_eq!(device.inits(), 1);
+        assert_eq!(device.polls(), 1);
+        assert_eq!(backend.device_info(), None);
+    }
+
+    #[test]
+    fn handshake_device_info_enables_status_byte() {
+        let device = EmulatedDevice::spawn(FrameLayout {
+            status_byte: true,
+            device_info: Some(DeviceInfo {
+                channels: 4,
+                bit_depth: 12,
+                status_byte: true,
+            }),
+            ..FrameLayout::default()
+        })
+        .unwrap();
+        device.set_values(&[10, 20, 30, 40]);
+        device.set_flags(DeviceFlags::LOW_BATTERY);
+        let mut backend = emulated_backend(&device);
+
+        poll_after_delay(&mut backend);
+
+        assert!(backend.status_byte());
+        assert!(backend.device_flags().low_battery());
+        assert!(!backend.device_flags().over_temp());
+        assert_eq!(backend.device_info().map(|info| info.bit_depth), Some(12));
+    }
+
+    #[test]
+    fn status_byte_missing_from_frame_is_an_error() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        let mut backend = emulated_backend(&device);
+        backend.set_status_byte(true);
+
+        thread::sleep(POLL_DELAY * 2);
+        assert!(matches!(
+            backend.poll(),
+            Err(ViewerBackendError::ParserError(_))
+        ));
+    }
+
+    #[test]
+    fn oversized_frame_is_flagged_as_truncated() {
+        let device = EmulatedDevice::spawn(FrameLayout {
+            padding: MAX_CHANNELS * 2 + MAX_TRAILER_LEN,
+            ..FrameLayout::default()
+        })
+        .unwrap();
+        device.set_values(&[1, 2, 3, 4]);
+        let mut backend = emulated_backend(&device);
+
+        thread::sleep(POLL_DELAY * 2);
+        assert!(matches!(
+            backend.poll(),
+            Err(ViewerBackendError::Truncated(amt)) if amt == MAX_CHANNELS * 2 + MAX_TRAILER_LEN + 1
+        ));
+        assert_eq!(backend.value_revision(), 0);
+    }
+
+    #[test]
+    fn silent_device_times_out() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        device.set_silent(true);
+        let mut backend = emulated_backend_with(
+            &device,
+            ViewerBackendConfig {
+                read_timeout: Some(Duration::from_millis(20)),
+                ..ViewerBackendConfig::default()
+            },
+        );
+
+        thread::sleep(POLL_DELAY * 2);
+        assert!(matches!(
+            backend.poll(),
+            Err(ViewerBackendError::Timeout(waited)) if waited == Duration::from_millis(20)
+        ));
+        assert_eq!(backend.stats().timeouts, 1);
+        assert_eq!(backend.stats().failures, 0);
+    }
+
+    #[test]
+    fn all_zero_reading_is_valid_data() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        let mut backend = emulated_backend(&device);
+        assert!(backend.read().is_err());
+
+        poll_after_delay(&mut backend);
+        assert_eq!(
+            backend.read().unwrap(),
+            &AnalogValues {
+                channels: vec![0, 0, 0, 0],
+            }
+        );
+    }
+
+    #[test]
+    fn short_frame_is_rejected() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        // only two of the four channels
+        device.set_reply(&[0, 1, 0, 2]);
+        let mut backend = emulated_backend(&device);
+
+        thread::sleep(POLL_DELAY * 2);
+        assert!(matches!(
+            backend.poll(),
+            Err(ViewerBackendError::ParserError(_))
+        ));
+        assert!(!backend.has_data);
+    }
+
+    #[test]
+    fn poll_decodes_every_channel_in_the_frame() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        let eight = [0, 1, 2, 3, 4, 5, 6, 4095];
+        device.set_values(&eight);
+        let mut backend = emulated_backend(&device);
+
+        assert_eq!(poll_after_delay(&mut backend).channels, eight);
+        assert_eq!(backend.frame_len(), 16);
+    }
+
+    #[test]
+    fn recording_writes_a_row_per_poll() {
+        let path = std::env::temp_dir().join(format!("recording-{}.csv", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        device.set_values(&[1, 2, 3, 4]);
+        let mut backend = emulated_backend(&device);
+        backend.set_filter_alpha(0, 0.5);
+        backend.start_recording(&path).unwrap();
+        poll_after_delay(&mut backend);
+        device.set_values(&[101, 2, 3, 4]);
+        poll_after_delay(&mut backend);
+        backend.stop_recording();
+
+        let csv = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[0], "timestamp_ms,a0,a1,a2,a3");
+        assert!(lines[1].ends_with(",1,2,3,4"));
+        // what the device sent, not the filtered 51 the gauge shows
+        assert!(lines[2].ends_with(",101,2,3,4"));
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[test]
+    fn read_calibrated_applies_each_channels_calibration() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        device.set_values(&[100, 100, 0, 0]);
+        let mut backend = emulated_backend(&device);
+        backend.set_calibration(1, "2:-50".parse().unwrap());
+        poll_after_delay(&mut backend);
+
+        let mv = backend.read_calibrated().unwrap();
+        assert_eq!(mv[0], Calibration::default().to_mv(100.0));
+        assert_eq!(mv[1], 150.0);
+        assert_eq!(backend.calibration(1).to_raw(150.0), 100.0);
+    }
+
+    #[test]
+    fn filter_averages_until_reconnect() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        let mut backend = emulated_backend(&device);
+        backend.set_filter_alpha(0, 0.5);
+        poll_after_delay(&mut backend);
+
+        device.set_values(&[100, 100, 0, 0]);
+        let values = poll_after_delay(&mut backend);
+        assert_eq!(values.channels[..2], [50, 100]);
+        // the counts the device sent are kept as they were
+        assert_eq!(backend.read_raw().unwrap().channels[..2], [100, 100]);
+        let (_, latest) = backend.history().last().unwrap();
+        assert_eq!(latest.channels[..2], [100, 100]);
+
+        backend.reconnect().unwrap();
+        assert_eq!(poll_after_delay(&mut backend)[0], 100);
+    }
+
+    #[test]
+    fn history_drops_samples_older_than_the_window() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        let mut backend = emulated_backend(&device);
+        backend.set_history_window(Some(Duration::from_millis(50)));
+        poll_after_delay(&mut backend);
+        poll_after_delay(&mut backend);
+        assert_eq!(backend.history().len(), 2);
+
+        thread::sleep(Duration::from_millis(60));
+        device.set_values(&[7, 0, 0, 0]);
+        poll_after_delay(&mut backend);
+
+        let history = backend.history();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].1[0], 7);
+    }
+
+    #[test]
+    fn shutdown_tells_the_device_to_stop() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        let mut backend = emulated_backend(&device);
+        poll_after_delay(&mut backend);
+        backend.shutdown().unwrap();
+
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(device.stops(), 1);
+    }
+
+    #[test]
+    fn channel_outside_its_thresholds_is_in_alarm() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        device.set_values(&[100, 100, 100, 100]);
+        let mut backend = emulated_backend(&device);
+        let identity = Calibration {
+            scale: 1.0,
+            offset: 0.0,
+        };
+        for channel in 0..3 {
+            backend.set_calibration(channel, identity);
+        }
+        backend.set_thresholds(0, ":50".parse().unwrap());
+        backend.set_thresholds(1, "50:150".parse().unwrap());
+        backend.set_thresholds(2, "150:".parse().unwrap());
+        poll_after_delay(&mut backend);
+
+        assert!(backend.in_alarm(0));
+        assert!(!backend.in_alarm(1));
+        assert!(backend.in_alarm(2));
+        assert!(!backend.in_alarm(3));
+    }
+
+    #[test]
+    fn failed_polls_back_off() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        device.set_silent(true);
+        let mut backend = emulated_backend_with(
+            &device,
+            ViewerBackendConfig {
+                read_timeout: Some(Duration::from_millis(5)),
+                ..ViewerBackendConfig::default()
+            },
+        );
+
+        thread::sleep(POLL_DELAY * 2);
+        assert!(backend.poll().is_err());
+        assert!(backend.next_poll_in() > RETRY_BACKOFF_MIN / 2);
+
+        // too soon, the device isn't asked again
+        assert!(backend.poll().is_ok());
+        assert_eq!(backend.stats().timeouts, 1);
+        assert_eq!(device.polls(), 1);
+
+        thread::sleep(backend.next_poll_in());
+        assert!(backend.poll().is_err());
+        assert!(backend.next_poll_in() > RETRY_BACKOFF_MIN);
+
+        backend.reconnect().unwrap();
+        assert!(backend.next_poll_in() <= POLL_DELAY);
+    }
+
+    #[test]
+    fn late_frame_is_dropped() {
+        let device = EmulatedDevice::spawn(FrameLayout {
+            sequence_byte: true,
+            checksum_byte: true,
+            ..FrameLayout::default()
+        })
+        .unwrap();
+        device.set_values(&[1, 2, 3, 4]);
+        let mut backend = emulated_backend(&device);
+        backend.set_sequence_byte(true);
+        backend.set_checksum_byte(true);
+        assert_eq!(backend.frame_len(), 10);
+        poll_after_delay(&mut backend);
+        poll_after_delay(&mut backend);
+
+        device.set_sequence(0);
+        device.set_values(&[5, 6, 7, 8]);
+        assert_eq!(poll_after_delay(&mut backend).channels, [1, 2, 3, 4]);
+        assert_eq!(backend.stats().out_of_order, 1);
+    }
+
+    #[test]
+    fn checksum_mismatch_is_an_error() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        // the four values add up to 10, not 11
+        device.set_reply(&[0, 1, 0, 2, 0, 3, 0, 4, 11]);
+        let mut backend = emulated_backend(&device);
+        backend.set_checksum_byte(true);
+
+        thread::sleep(POLL_DELAY * 2);
+        assert!(matches!(
+            backend.poll(),
+            Err(ViewerBackendError::ParserError(_))
+        ));
+    }
+
+    #[test]
+    fn text_mode_polls_through_the_text_parser() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        device.set_reply(b"a0:1234:a1:0:a2:4095:a3:17\r\n");
+        let mut backend = emulated_backend(&device);
+        backend.set_protocol(ProtocolMode::Text, ExtraFields::default());
+
+        assert_eq!(poll_after_delay(&mut backend), text_frame_values());
+    }
+
+    #[test]
+    fn discover_finds_the_device_address() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+
+        assert_eq!(
+            ViewerBackend::discover_at(device.addr(), Duration::from_millis(50)).unwrap(),
+            device.addr()
+        );
+    }
+
+    #[test]
+    fn extremes_hold_until_reset() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        let mut backend = emulated_backend(&device);
+        device.set_values(&[10, 0, 0, 0]);
+        poll_after_delay(&mut backend);
+        device.set_values(&[30, 0, 0, 0]);
+        poll_after_delay(&mut backend);
+        device.set_values(&[20, 0, 0, 0]);
+        poll_after_delay(&mut backend);
+        assert_eq!(backend.extremes(0), Some((10, 30)));
+
+        backend.reset_extremes();
+        assert_eq!(backend.extremes(0), None);
+        poll_after_delay(&mut backend);
+        assert_eq!(backend.extremes(0), Some((20, 20)));
+    }
+
+    #[test]
+    fn connection_state_follows_polls() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        let mut backend = emulated_backend(&device);
+        assert_eq!(backend.connection_state(), ConnectionState::Connecting);
+        poll_after_delay(&mut backend);
+        assert_eq!(backend.connection_state(), ConnectionState::Connected);
+
+        device.set_silent(true);
+        backend
+            .transport
+            .set_read_timeout(Some(Duration::from_millis(5)))
+            .unwrap();
+        thread::sleep(POLL_DELAY * 2);
+        assert!(backend.poll().is_err());
+        assert_eq!(backend.connection_state(), ConnectionState::Lost);
+    }
+
+    #[test]
+    fn temperature_transform_reads_the_on_die_sensor() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        device.set_values(&[100, 100, 0, 0]);
+        let mut backend = emulated_backend(&device);
+        // 0.706 V is 27 °C, every 1.721 mV below that is another degree
+        backend.set_calibration(1, "1:606".parse().unwrap());
+        backend.set_transform(1, DisplayTransform::Temperature);
+        backend.set_transform(2, "custom:2:5".parse().unwrap());
+        backend.set_thresholds(1, ":30".parse().unwrap());
+        poll_after_delay(&mut backend);
+
+        let shown = backend.read_display().unwrap();
+        assert_eq!(shown[0], backend.read_calibrated().unwrap()[0]);
+        assert!((shown[1] - 27.0).abs() < 0.01);
+        assert_eq!(shown[2], 5.0);
+        assert!(!backend.in_alarm(1));
+        assert!((DisplayTransform::Temperature.invert(27.0) - 706.0).abs() < 0.01);
+        assert_eq!(DisplayTransform::Temperature.unit(), Some("°C"));
+    }
+
+    #[test]
+    fn polls_over_ipv6() {
+        let device = EmulatedDevice::spawn_at(
+            SocketAddr::from((Ipv6Addr::LOCALHOST, 0)),
+            FrameLayout::default(),
+        )
+        .unwrap();
+        device.set_values(&[1, 2, 3, 4]);
+        let remote_addr = device.addr();
+        let mut backend = emulated_backend(&device);
+
+        assert_eq!(poll_after_delay(&mut backend).channels, [1, 2, 3, 4]);
+        assert_eq!(
+            resolve_addr(&format!(" {} ", remote_addr)).unwrap(),
+            remote_addr
+        );
+        assert_eq!(resolve_addr("localhost:4000").unwrap().port(), 4000);
+    }
+
+    #[test]
+    fn replay_plays_a_recording_back() {
+        let path = std::env::temp_dir().join(format!("replay-{}.csv", std::process::id()));
+        // the second row was averaged, the third is from a session appended later
+        std::fs::write(
+            &path,
+            "timestamp_ms,a0,a1\n100,10,20\n120,30,40.6\n5,50,60\n",
+        )
+        .unwrap();
+        let mut backend = ViewerBackend::replay(&path, ReplayConfig::default()).unwrap();
+        let _ = std::fs::remove_file(&path);
+        backend.connect_socket().unwrap();
+
+        assert_eq!(backend.poll().unwrap().channels, [10, 20]);
+        assert_eq!(backend.poll().unwrap().channels, [30, 41]);
+        assert_eq!(backend.poll().unwrap().channels, [50, 60]);
+        assert_eq!(backend.extremes(0), Some((10, 50)));
+        assert!(matches!(
+            backend.poll(),
+            Err(ViewerBackendError::SocketError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof
+        ));
+    }
+
+    #[test]
+    fn try_poll_returns_none_until_the_reply_arrives() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        device.set_values(&[1, 2, 3, 4]);
+        device.set_reply_delay(Duration::from_millis(50));
+        let mut backend = emulated_backend(&device);
+        thread::sleep(POLL_DELAY * 2);
+
+        assert_eq!(backend.try_poll().unwrap(), None);
+        // still waiting, without sending the request again
+        assert_eq!(backend.try_poll().unwrap(), None);
+
+        thread::sleep(Duration::from_millis(100));
+        assert_eq!(
+            backend
+                .try_poll()
+                .unwrap()
+                .map(|vals| vals.channels.clone()),
+            Some(vec![1, 2, 3, 4])
+        );
+        assert_eq!(backend.stats().polls, 1);
+        assert_eq!(device.polls(), 1);
+    }
+
+    #[test]
+    fn lost_init_is_retried_until_acknowledged() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        device.set_values(&[7, 8, 9, 10]);
+        device.lose_inits(1);
+        let mut backend = emulated_backend(&device);
+
+        assert_eq!(poll_after_delay(&mut backend).channels, vec![7, 8, 9, 10]);
+        assert_eq!(device.inits(), 2);
+        let kinds: Vec<ConnectionEventKind> = backend
+            .connection_history()
+            .into_iter()
+            .map(|event| event.kind)
+            .collect();
+        assert!(kinds.contains(&ConnectionEventKind::InitSent { attempt: 2 }));
+        assert!(kinds.contains(&ConnectionEventKind::InitAcked));
+
+        // a reconnect shakes hands again, and gives up once every attempt is lost
+        device.lose_inits(3);
+        backend.reconnect().unwrap();
+        thread::sleep(POLL_DELAY * 2);
+        assert!(matches!(
+            backend.poll(),
+            Err(ViewerBackendError::Timeout(_))
+        ));
+        assert_eq!(device.inits(), 5);
+        assert_eq!(device.polls(), 1);
+    }
+
+    #[test]
+    fn disabled_channel_is_decoded_but_never_alarms() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        device.set_values(&[100, 100, 100, 100]);
+        let mut backend = emulated_backend(&device);
+        for channel in 0..2 {
+            backend.set_thresholds(channel, ":0".parse().unwrap());
+        }
+        backend.set_channel_enabled(1, false);
+
+        assert_eq!(poll_after_delay(&mut backend)[1], 100);
+        assert!(backend.in_alarm(0));
+        assert!(!backend.in_alarm(1));
+        assert_eq!(backend.extremes(0), Some((100, 100)));
+        assert_eq!(backend.extremes(1), None);
+    }
+
+    #[test]
+    fn logarithmic_transform_floors_instead_of_going_to_minus_infinity() {
+        let transform: DisplayTransform = "log:3300".parse().unwrap();
+        assert_eq!(
+            transform,
+            DisplayTransform::Logarithmic { reference: 3300.0 }
+        );
+        assert_eq!(transform.apply(3300.0), 0.0);
+        assert!((transform.apply(330.0) + 20.0).abs() < 1e-4);
+        assert_eq!(transform.apply(0.0), LOG_FLOOR_DB);
+        assert_eq!(transform.apply(-5.0), LOG_FLOOR_DB);
+        assert!((transform.invert(-20.0) - 330.0).abs() < 1e-2);
+        assert!("log:0".parse::<DisplayTransform>().is_err());
+    }
+
+    #[test]
+    fn window_average_covers_only_recent_samples() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        let mut backend = emulated_backend(&device);
+        backend.set_history_window(Some(Duration::from_secs(600)));
+        assert_eq!(backend.window_average(0, Duration::from_secs(1)), 0.0);
+
+        // timestamps far enough apart that a slow test run can't blur them
+        let now = Instant::now();
+        let minute_ago = now.checked_sub(Duration::from_secs(60)).unwrap();
+        for (at, value) in [(minute_ago, 100), (now, 200), (now, 400)] {
+            let values = AnalogValues {
+                channels: vec![value, 0, 0, 0],
+            };
+            backend.history.push((at, values.clone()));
+            backend.analog_vals = values;
+        }
+
+        assert_eq!(
+            backend.window_average(0, Duration::from_secs(120)),
+            700.0 / 3.0
+        );
+        assert_eq!(backend.window_average(0, Duration::from_secs(30)), 300.0);
+        // nothing that recent, so the current value
+        assert_eq!(backend.window_average(0, Duration::ZERO), 400.0);
+    }
+
+    #[test]
+    fn long_gap_between_polls_redoes_the_handshake() {
+        // whether or not the device acks, it's sent init again
+        for attempts in [INIT_ATTEMPTS, 0] {
+            let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+            if attempts == 0 {
+                // firmware that never answers init
+                device.lose_inits(u32::MAX);
+            }
+            let mut backend = emulated_backend(&device);
+            backend.set_init_attempts(attempts);
+            backend.set_stall_timeout(Some(Duration::from_millis(250)));
+
+            poll_after_delay(&mut backend);
+            poll_after_delay(&mut backend);
+            assert_eq!(device.inits(), 1);
+
+            thread::sleep(Duration::from_millis(300));
+            poll_after_delay(&mut backend);
+            assert_eq!(device.inits(), 2);
+            assert!(backend
+                .connection_history()
+                .iter()
+                .any(|event| matches!(event.kind, ConnectionEventKind::PollGap(_))));
+        }
+    }
+
+    /// little-endian 12-bit samples packed two to three bytes, like some custom firmware sends
+    struct Packed12Decoder;
+
+    impl PacketDecoder for Packed12Decoder {
+        fn decode(&self, buf: &[u8], channels: usize) -> Result<AnalogValues, ViewerBackendError> {
+            if buf.len() != self.values_len(channels) {
+                Err(ViewerBackendError::ParserError(String::from(
+                    "wrong length",
+                )))?
+            }
+            let mut values = Vec::new();
+            for triple in buf.chunks_exact(3) {
+                let bits = u32::from_le_bytes([triple[0], triple[1], triple[2], 0]);
+                values.extend([(bits & 0xfff) as u16, (bits >> 12) as u16]);
+            }
+            values.truncate(channels);
+            Ok(AnalogValues { channels: values })
+        }
+
+        fn values_len(&self, channels: usize) -> usize {
+            channels.div_ceil(2) * 3
+        }
+    }
+
+    #[test]
+    fn custom_decoder_reads_its_own_wire_format() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        // 0x123, 0xabc, 0xfff, 0x001 then a status byte with the low battery bit
+        device.set_reply(&[0x23, 0xc1, 0xab, 0xff, 0x1f, 0x00, 0x02]);
+        let mut backend = emulated_backend(&device);
+        backend.set_decoder(Box::new(Packed12Decoder));
+        backend.set_status_byte(true);
+        assert_eq!(backend.frame_len(), 7);
+
+        assert_eq!(
+            poll_after_delay(&mut backend).channels,
+            [0x123, 0xabc, 0xfff, 0x001]
+        );
+        assert!(backend.device_flags().low_battery());
+    }
+
+    #[test]
+    fn interval_stats_follow_the_poll_spacing() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        let mut backend = emulated_backend(&device);
+        assert_eq!(backend.stats().interval_mean, Duration::ZERO);
+
+        poll_after_delay(&mut backend);
+        poll_after_delay(&mut backend);
+        assert_eq!(backend.intervals.len(), 1);
+
+        // the spacing itself is made up, real sleeps would depend on the scheduler
+        backend.intervals.clear();
+        for _ in 0..12 {
+            backend.record_interval(Duration::from_millis(10));
+        }
+        let stats = backend.stats();
+        assert_eq!(stats.interval_mean, Duration::from_millis(10));
+        assert_eq!(stats.interval_std_dev, Duration::ZERO);
+        assert_eq!(stats.hitches, 0);
+
+        backend.record_interval(Duration::from_millis(25));
+        assert_eq!(backend.stats().hitches, 0);
+        backend.record_interval(Duration::from_millis(200));
+        assert_eq!(backend.stats().hitches, 1);
+        assert!(backend.stats().interval_std_dev > Duration::ZERO);
+    }
+
+    #[test]
+    fn full_cycle_against_a_loopback_device() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        device.set_values(&[1, 256, 4095, 2048]);
+        let mut backend = emulated_backend(&device);
+        assert!(backend.read().is_err());
+
+        // one init, then one poll answered with the legacy 8 byte frame, just like the firmware
+        poll_after_delay(&mut backend);
+        assert_eq!(device.inits(), 1);
+        assert_eq!(device.polls(), 1);
+
+        assert_eq!(
+            backend.read().unwrap(),
+            &AnalogValues {
+                channels: vec![1, 256, 4095, 2048],
+            }
+        );
+        assert_eq!(backend.connection_state(), ConnectionState::Connected);
+    }
+
+    #[test]
+    fn commands_need_a_connected_socket_and_can_wait_for_an_ack() {
+        let device = EmulatedDevice::spawn(FrameLayout {
+            command_acks: true,
+            ..FrameLayout::default()
+        })
+        .unwrap();
+        let mut unconnected = ViewerBackend::connect_with(ViewerBackendConfig {
+            local_addr: SocketAddr::from((Ipv4Addr::LOCALHOST, 0)),
+            remote_addr: device.addr(),
+            ..ViewerBackendConfig::default()
+        })
+        .unwrap();
+        assert!(matches!(
+            unconnected.send_command("gain:2"),
+            Err(ViewerBackendError::ParserError(_))
+        ));
+
+        let mut backend = emulated_backend(&device);
+        backend.set_command_acks(true);
+        backend.send_command("gain:2").unwrap();
+        assert_eq!(device.commands(), [b"gain:2".to_vec()]);
+    }
+
+    #[test]
+    fn vref_moves_only_the_uncalibrated_channels() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        device.set_values(&[2048, 2048, 0, 0]);
+        let mut backend = emulated_backend(&device);
+        backend.set_calibration(1, "2:-50".parse().unwrap());
+        backend.set_vref_mv(3000.0);
+        poll_after_delay(&mut backend);
+
+        let mv = backend.read_calibrated().unwrap();
+        assert_eq!(backend.vref_mv(), 3000.0);
+        assert_eq!(mv[0], 1500.0);
+        assert_eq!(mv[1], 4046.0);
+
+        backend.set_vref_mv(VREF_MV);
+        assert_eq!(backend.calibration(0), Calibration::default());
+    }
+
+    /// a link that's up but refuses every send
+    struct SendFails(Option<Duration>);
+
+    impl Transport for SendFails {
+        fn send(&mut self, _msg: &[u8]) -> std::io::Result<()> {
+            Err(std::io::Error::from(std::io::ErrorKind::ConnectionRefused))
+        }
+
+        fn recv(&mut self, _buf: &mut [u8], _frame_len: usize) -> std::io::Result<usize> {
+            Err(std::io::Error::from(std::io::ErrorKind::TimedOut))
+        }
+
+        fn read_timeout(&self) -> std::io::Result<Option<Duration>> {
+            Ok(self.0)
+        }
+
+        fn set_read_timeout(&mut self, timeout: Option<Duration>) -> std::io::Result<()> {
+            self.0 = timeout;
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn failed_init_send_restores_the_poll_timeout() {
+        let mut backend = ViewerBackend::with_transport(
+            Box::new(SendFails(Some(READ_TIMEOUT))),
+            &ViewerBackendConfig::default(),
+        );
+        backend.set_init_attempts(3);
+
+        assert!(matches!(
+            backend.initialize(),
+            Err(ViewerBackendError::SocketError(_))
+        ));
+        assert_eq!(
+            backend.transport.read_timeout().unwrap(),
+            Some(READ_TIMEOUT)
+        );
+    }
+
+    #[test]
+    fn default_handshake_waits_for_the_ack() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        device.set_values(&[1, 2, 3, 4]);
+        device.lose_inits(INIT_ATTEMPTS);
+        let mut backend = emulated_backend(&device);
+
+        thread::sleep(POLL_DELAY * 2);
+        assert!(matches!(
+            backend.poll(),
+            Err(ViewerBackendError::Timeout(_))
+        ));
+        assert!(!backend.initialized);
+        assert_eq!(device.polls(), 0);
+
+        // the retry starts the handshake over and gets through this time
+        thread::sleep(backend.next_poll_in());
+        assert_eq!(poll_after_delay(&mut backend).channels, [1, 2, 3, 4]);
+        assert_eq!(device.inits(), INIT_ATTEMPTS + 1);
+    }
+
+    #[test]
+    fn channel_counts_past_the_limit_are_rejected() {
+        assert!(matches!(
+            DeviceInfo::from_ack(b"ack:300:12:0"),
+            Err(ViewerBackendError::ParserError(_))
+        ));
+        assert!(matches!(
+            DeviceInfo::from_ack(b"ack:17:12:0"),
+            Err(ViewerBackendError::ParserError(_))
+        ));
+        assert!(matches!(
+            DeviceInfo::from_ack(b"ack:4:300:0"),
+            Err(ViewerBackendError::ParserError(_))
+        ));
+        assert_eq!(
+            DeviceInfo::from_ack(b"ack:16:12:1")
+                .unwrap()
+                .map(|info| info.channels),
+            Some(16)
+        );
+
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        device.set_values(&[1; MAX_CHANNELS + 1]);
+        let mut backend = emulated_backend(&device);
+        thread::sleep(POLL_DELAY * 2);
+        assert!(matches!(
+            backend.poll(),
+            Err(ViewerBackendError::ParserError(_))
+        ));
+        assert!(backend.channels <= MAX_CHANNELS);
+        assert!(!backend.in_alarm(MAX_CHANNELS - 1));
+    }
+
+    #[test]
+    fn integral_accumulates_value_times_interval_and_resets() {
+        let device = EmulatedDevice::spawn(FrameLayout::default()).unwrap();
+        device.set_values(&[1000, 500, 0, 0]);
+        let mut backend = emulated_backend(&device);
+        backend.set_integral_enabled(0, true);
+
+        // the first sample has nothing to integrate over
+        poll_after_delay(&mut backend);
+        assert_eq!(backend.integral(0), 0.0);
+
+        // as if the last poll was two seconds ago, rather than sleeping that long
+        let gap = Duration::from_secs(2);
+        let poll_spaced_by = |backend: &mut ViewerBackend| {
+            let started = Instant::now();
+            backend.last_poll = started - gap;
+            backend.poll().unwrap();
+            (gap.as_secs_f64(), (gap + started.elapsed()).as_secs_f64())
+        };
+
+        let (min, max) = poll_spaced_by(&mut backend);
+        let integral = backend.integral(0);
+        assert!(
+            (1000.0 * min..=1000.0 * max).contains(&integral),
+            "{}",
+            integral
+        );
+        assert_eq!(backend.integral(1), 0.0);
+
+        // a disabled channel's integral holds where it was
+        backend.set_channel_enabled(0, false);
+        let held = backend.integral(0);
+        poll_spaced_by(&mut backend);
+        assert_eq!(backend.integral(0), held);
+        backend.set_channel_enabled(0, true);
+
+        let (more_min, more_max) = poll_spaced_by(&mut backend);
+        let integral = backend.integral(0);
+        let range = 1000.0 * (min + more_min)..=1000.0 * (max + more_max);
+        assert!(range.contains(&integral), "{}", integral);
+
+        backend.reset_integral(0);
+        assert_eq!(backend.integral(0), 0.0);
+        let (min, max) = poll_spaced_by(&mut backend);
+        let integral = backend.integral(0);
+        assert!(
+            (1000.0 * min..=1000.0 * max).contains(&integral),
+            "{}",
+            integral
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use crate::backend::{AnalogValues, ExtraFields, ViewerBackend, ViewerBackendError, MAX_CHANNELS};
+
+/// turns the values in a reply to `poll` into readings
+/// the status, sequence and checksum bytes around them stay the backend's business
+pub trait PacketDecoder: Send + Sync {
+    /// decode at least `channels` values from `buf`, any more the device sent are decoded too
+    fn decode(&self, buf: &[u8], channels: usize) -> Result<AnalogValues, ViewerBackendError>;
+
+    /// bytes `channels` values take up, what the frame spec expects
+    fn values_len(&self, channels: usize) -> usize {
+        channels * 2
+    }
+
+    /// longest reply worth receiving, anything longer counts as truncated
+    fn max_frame_len(&self) -> usize {
+        MAX_CHANNELS * 2
+    }
+
+    /// whether the frame options (status, sequence and checksum bytes) apply to its frames
+    fn binary(&self) -> bool {
+        true
+    }
+}
+
+/// big-endian u16 channels, one per byte pair, as the stock firmware sends them
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BeU16Decoder;
+
+impl PacketDecoder for BeU16Decoder {
+    /// errors if there are fewer than `channels` pairs or a byte is left over
+    fn decode(&self, buf: &[u8], channels: usize) -> Result<AnalogValues, ViewerBackendError> {
+        if !buf.len().is_multiple_of(2) {
+            Err(ViewerBackendError::ParserError(format!(
+                "odd number of value bytes, got {}, is a status, sequence or checksum byte missing?",
+                buf.len()
+            )))?
+        }
+        if buf.len() < channels * 2 {
+            Err(ViewerBackendError::ParserError(format!(
+                "short frame, got {} of {} value bytes",
+                buf.len(),
+                channels * 2
+            )))?
+        }
+
+        Ok(AnalogValues {
+            channels: buf
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect(),
+        })
+    }
+}
+
+/// `a0:<value>:a1:<value>:...` in ASCII, as older firmware sends it
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TextDecoder {
+    pub extra: ExtraFields,
+}
+
+impl PacketDecoder for TextDecoder {
+    fn decode(&self, buf: &[u8], channels: usize) -> Result<AnalogValues, ViewerBackendError> {
+        let text = std::str::from_utf8(buf).map_err(|e| {
+            ViewerBackendError::ParserError(format!("text frame is not utf-8: {:?}", e))
+        })?;
+        ViewerBackend::parse_text_frame(text, channels, self.extra)
+    }
+
+    /// all channels as `a15:65535:` plus a line terminator
+    fn max_frame_len(&self) -> usize {
+        MAX_CHANNELS * 10 + 2
+    }
+
+    fn binary(&self) -> bool {
+        false
+    }
+}
@@ -0,0 +1,552 @@
+use std::fmt::Write;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+use crate::backend::MAX_CHANNELS;
+
+/// raw value at which the needle reaches the end of the dial, unless a channel sets its own
+pub static FULL_SCALE: f32 = 4096.0;
+
+/// nominal ADC reference voltage, the default calibration assumes it
+pub static VREF_MV: f32 = 3333.0;
+
+/// how far the needle sweeps across the dial artwork, in degrees
+pub static NEEDLE_SWEEP_DEG: f32 = 260.0;
+
+/// how a channel's value is turned into a needle position
+#[derive(Debug, Clone, Default)]
+pub enum NeedleMap {
+    /// needle moves proportionally across the channel's range, 0 to its full scale by default
+    #[default]
+    Linear,
+    /// (value, angle in degrees) breakpoints, interpolated linearly in between
+    /// values outside the table are clamped to the first/last breakpoint
+    Table(Vec<(f32, f32)>),
+}
+
+impl NeedleMap {
+    /// fraction of the full needle sweep (0.0..=1.0) for the given value
+    /// `range` is the raw (min, max) span of the dial, tables place the needle themselves
+    pub fn angle_fraction(&self, value: f32, range: (f32, f32)) -> f32 {
+        let fraction = match self {
+            NeedleMap::Linear => (value - range.0) / (range.1 - range.0),
+            NeedleMap::Table(points) => Self::interpolate(points, value) / NEEDLE_SWEEP_DEG,
+        };
+
+        fraction.clamp(0.0, 1.0)
+    }
+
+    /// find the angle for a value in a table of breakpoints
+    fn interpolate(points: &[(f32, f32)], value: f32) -> f32 {
+        let (first, last) = match (points.first(), points.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return 0.0, // an empty table has nowhere to put the needle
+        };
+
+        if value <= first.0 {
+            return first.1;
+        }
+        if value >= last.0 {
+            return last.1;
+        }
+
+        for pair in points.windows(2) {
+            let ((v0, a0), (v1, a1)) = (pair[0], pair[1]);
+            if value <= v1 {
+                // value > v0 here, so v1 > v0 and the division is safe
+                return a0 + (a1 - a0) * (value - v0) / (v1 - v0);
+            }
+        }
+
+        last.1
+    }
+}
+
+/// parses either `linear` or a table of `value:angle` breakpoints separated by commas,
+/// e.g. `0:0,1024:130,4096:260`
+impl FromStr for NeedleMap {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.eq_ignore_ascii_case("linear") {
+            return Ok(NeedleMap::Linear);
+        }
+
+        let mut points = s
+            .split(',')
+            .map(|point| {
+                let (value, angle) = point
+                    .split_once(':')
+                    .ok_or(format!("breakpoint {:?} is not value:angle", point))?;
+                Ok((
+                    parse_float(value, "breakpoint value")?,
+                    parse_float(angle, "breakpoint angle")?,
+                ))
+            })
+            .collect::<Result<Vec<(f32, f32)>, String>>()?;
+
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        Ok(NeedleMap::Table(points))
+    }
+}
+
+/// parse one number of a breakpoint or range, naming it in the error message
+fn parse_float(s: &str, name: &str) -> Result<f32, String> {
+    s.trim()
+        .parse()
+        .map_err(|e| format!("invalid {} {:?}: {:?}", name, s, e))
+}
+
+/// parse a `min:max` raw range
+fn parse_range(s: &str) -> Result<(f32, f32), String> {
+    let (min, max) = s
+        .split_once(':')
+        .ok_or(format!("range {:?} is not min:max", s))?;
+    let (min, max) = (parse_float(min, "min")?, parse_float(max, "max")?);
+
+    if min >= max {
+        return Err(format!("range {:?} is empty", s));
+    }
+    Ok((min, max))
+}
+
+/// how one gauge is labelled and scaled
+#[derive(Debug, Clone)]
+pub struct ChannelDisplay {
+    pub label: String,
+    /// unit of the calibrated value, the calibration has to convert into it
+    pub unit: String,
+    /// raw value at which the needle reaches the end of the dial, unless a range is set
+    pub full_scale: f32,
+}
+
+impl Default for ChannelDisplay {
+    fn default() -> Self {
+        ChannelDisplay {
+            label: String::new(),
+            unit: String::from("mV"),
+            full_scale: FULL_SCALE,
+        }
+    }
+}
+
+impl ChannelDisplay {
+    /// `raw` limited to `[0, full_scale]` for showing, and whether it was outside
+    /// a device with another ADC width or a glitch can send anything up to 0xFFFF
+    pub fn clamp(&self, raw: f32) -> (f32, bool) {
+        let clamped = raw.clamp(0.0, self.full_scale);
+        (clamped, clamped != raw)
+    }
+}
+
+/// per-channel display settings
+#[derive(Debug, Clone, Default)]
+pub struct DisplayConfig {
+    pub channels: [ChannelDisplay; MAX_CHANNELS],
+    pub needle_maps: [NeedleMap; MAX_CHANNELS],
+    /// raw (min, max) span of each dial, takes precedence over auto ranging
+    pub ranges: [Option<(f32, f32)>; MAX_CHANNELS],
+    /// how fast per second auto ranged dials narrow after an extreme, `None` for fixed dials
+    pub auto_range_rate: Option<f32>,
+    /// channels whose running integral is computed and shown
+    pub integrated: [bool; MAX_CHANNELS],
+    /// expected displayed value of each channel, marked on the dial while calibrating
+    pub references: [Option<f32>; MAX_CHANNELS],
+    /// raw values forced onto channels instead of the live reading, for demos
+    pub overrides: [Option<u16>; MAX_CHANNELS],
+    /// time constant of the cosmetic needle smoothing, zero shows values as they arrive
+    pub smoothing: Duration,
+    /// counts a channel has to move by before the gauges are redrawn for it, 0 redraws on any change
+    pub min_deltas: [u16; MAX_CHANNELS],
+    /// show the raw ADC count next to the converted value, for checking calibration
+    pub show_raw: bool,
+    /// show the average over this long under each gauge, `None` hides it
+    pub average_window: Option<Duration>,
+    /// replaces the built-in gauge.png when set
+    pub face_image: Option<PathBuf>,
+    /// replaces the built-in needle.png when set
+    pub needle_image: Option<PathBuf>,
+}
+
+/// artwork the gauges should be drawn with
+pub enum GaugeArtwork {
+    /// keep the images compiled into the binary
+    BuiltIn,
+    /// runtime-loaded images, `None` keeps the built-in one for that part
+    Images {
+        face: Option<slint::Image>,
+        needle: Option<slint::Image>,
+    },
+    /// a configured image could not be loaded, draw the vector gauge instead
+    Vector,
+}
+
+impl DisplayConfig {
+    /// read display settings from the environment, falling back to defaults
+    /// `NEEDLE_MAP_A0`..`NEEDLE_MAP_A15` select the needle map of each channel
+    /// `INTEGRATE_A0`..`INTEGRATE_A15=1` enable the running integral of each channel
+    /// `REFERENCE_A0`..`REFERENCE_A15` set the reference target of each channel in its unit
+    /// `LABEL_A0`..`LABEL_A15`, `UNIT_A0`..`UNIT_A15` and `FULL_SCALE_A0`..`FULL_SCALE_A15`
+    /// name each gauge, its unit and the raw value at the end of its dial
+    /// `OVERRIDE_A0`..`OVERRIDE_A15` force the displayed raw value of each channel
+    /// `DISPLAY_SMOOTHING_MS` sets the time constant of the needle smoothing
+    /// `MIN_DELTA_A0`..`MIN_DELTA_A15` skip redraws until the channel moves by that many counts
+    /// `RANGE_A0`..`RANGE_A15` fix the raw `min:max` span of each dial
+    /// `AUTO_RANGE_RATE` turns on auto ranging, narrowing by that fraction per second
+    /// `SHOW_RAW=1` shows the raw count alongside each converted value
+    /// `AVERAGE_WINDOW_MS` shows the average over that window under each gauge
+    pub fn from_env() -> Self {
+        let mut config = DisplayConfig::default();
+
+        for (i, channel) in config.channels.iter_mut().enumerate() {
+            channel.label = std::env::var(format!("LABEL_A{}", i)).unwrap_or(format!("a{}", i));
+            if let Ok(unit) = std::env::var(format!("UNIT_A{}", i)) {
+                channel.unit = unit;
+            }
+
+            let var = format!("FULL_SCALE_A{}", i);
+            if let Ok(full_scale) = std::env::var(&var) {
+                match parse_float(&full_scale, "full scale") {
+                    Ok(full_scale) if full_scale > 0.0 => channel.full_scale = full_scale,
+                    Ok(_) => log::warn!("ignoring {}: full scale must be positive", var),
+                    Err(e) => log::warn!("ignoring {}: {}", var, e),
+                }
+            }
+        }
+
+        for (i, map) in config.needle_maps.iter_mut().enumerate() {
+            let var = format!("NEEDLE_MAP_A{}", i);
+            if let Ok(spec) = std::env::var(&var) {
+                match spec.parse() {
+                    Ok(parsed) => *map = parsed,
+                    Err(e) => log::warn!("ignoring {}: {}", var, e),
+                }
+            }
+        }
+
+        for (i, reference) in config.references.iter_mut().enumerate() {
+            let var = format!("REFERENCE_A{}", i);
+            if let Ok(mv) = std::env::var(&var) {
+                match mv.trim().parse() {
+                    Ok(mv) => *reference = Some(mv),
+                    Err(e) => log::warn!("ignoring {}: {:?}", var, e),
+                }
+            }
+        }
+
+        for (i, value) in config.overrides.iter_mut().enumerate() {
+            let var = format!("OVERRIDE_A{}", i);
+            if let Ok(raw) = std::env::var(&var) {
+                match raw.trim().parse() {
+                    Ok(raw) => *value = Some(raw),
+                    Err(e) => log::warn!("ignoring {}: {:?}", var, e),
+                }
+            }
+        }
+
+        for (i, delta) in config.min_deltas.iter_mut().enumerate() {
+            let var = format!("MIN_DELTA_A{}", i);
+            if let Ok(counts) = std::env::var(&var) {
+                match counts.trim().parse() {
+                    Ok(counts) => *delta = counts,
+                    Err(e) => log::warn!("ignoring {}: {:?}", var, e),
+                }
+            }
+        }
+
+        for (i, range) in config.ranges.iter_mut().enumerate() {
+            let var = format!("RANGE_A{}", i);
+            if let Ok(spec) = std::env::var(&var) {
+                match parse_range(&spec) {
+                    Ok(parsed) => *range = Some(parsed),
+                    Err(e) => log::warn!("ignoring {}: {}", var, e),
+                }
+            }
+        }
+
+        if let Ok(rate) = std::env::var("AUTO_RANGE_RATE") {
+            match rate.trim().parse() {
+                Ok(rate) => config.auto_range_rate = Some(rate),
+                Err(e) => log::warn!("ignoring AUTO_RANGE_RATE: {:?}", e),
+            }
+        }
+
+        for (i, integrated) in config.integrated.iter_mut().enumerate() {
+            *integrated = std::env::var_os(format!("INTEGRATE_A{}", i)).is_some_and(|v| v == "1");
+        }
+
+        if let Ok(ms) = std::env::var("DISPLAY_SMOOTHING_MS") {
+            match ms.trim().parse() {
+                Ok(ms) => config.smoothing = Duration::from_millis(ms),
+                Err(e) => log::warn!("ignoring DISPLAY_SMOOTHING_MS: {:?}", e),
+            }
+        }
+
+        config.show_raw = std::env::var_os("SHOW_RAW").is_some_and(|v| v == "1");
+
+        if let Ok(ms) = std::env::var("AVERAGE_WINDOW_MS") {
+            match ms.trim().parse::<u64>() {
+                Ok(0) => {}
+                Ok(ms) => config.average_window = Some(Duration::from_millis(ms)),
+                Err(e) => log::warn!("ignoring AVERAGE_WINDOW_MS: {:?}", e),
+            }
+        }
+
+        config.face_image = std::env::var_os("GAUGE_FACE_IMAGE").map(PathBuf::from);
+        config.needle_image = std::env::var_os("GAUGE_NEEDLE_IMAGE").map(PathBuf::from);
+
+        config
+    }
+
+    /// whether any channel of `values` moved by at least its minimum delta from `pushed`
+    /// the values last drawn, a channel with a delta of 0 counts as soon as it changes at all
+    pub fn moved_enough(&self, pushed: &[u16], values: &[u16]) -> bool {
+        pushed.len() != values.len()
+            || pushed.iter().zip(values).zip(&self.min_deltas).any(
+                |((&pushed, &value), &min_delta)| {
+                    pushed != value && pushed.abs_diff(value) >= min_delta
+                },
+            )
+    }
+
+    /// raw (min, max) span of a channel's dial, from its manual range, the auto range or full scale
+    pub fn range(&self, channel: usize, auto_range: Option<(f32, f32)>) -> (f32, f32) {
+        self.ranges[channel]
+            .or(auto_range)
+            .unwrap_or((0.0, self.channels[channel].full_scale))
+    }
+
+    /// load the configured gauge images
+    /// falls back to the vector gauge rather than showing a blank dial if any of them fail
+    pub fn load_artwork(&self) -> GaugeArtwork {
+        if self.face_image.is_none() && self.needle_image.is_none() {
+            return GaugeArtwork::BuiltIn;
+        }
+
+        let face = self.face_image.as_deref().map(load_image);
+        let needle = self.needle_image.as_deref().map(load_image);
+
+        match (face, needle) {
+            (Some(None), _) | (_, Some(None)) => GaugeArtwork::Vector,
+            (face, needle) => GaugeArtwork::Images {
+                face: face.flatten(),
+                needle: needle.flatten(),
+            },
+        }
+    }
+}
+
+/// path commands drawing recent readings in a 100 x 100 viewbox, the newest at the right edge
+/// `samples` are (age in seconds, raw value) pairs, the width spans `window` seconds
+pub fn sparkline(
+    samples: impl IntoIterator<Item = (f32, f32)>,
+    window: Duration,
+    range: (f32, f32),
+) -> String {
+    let mut commands = String::new();
+    for (age, value) in samples {
+        let x = 100.0 * (1.0 - age / window.as_secs_f32()).clamp(0.0, 1.0);
+        let y = 100.0 * (1.0 - ((value - range.0) / (range.1 - range.0)).clamp(0.0, 1.0));
+        let command = if commands.is_empty() { "M" } else { "L" };
+        let _ = write!(commands, "{} {:.1} {:.1} ", command, x, y);
+    }
+    commands
+}
+
+/// load an image from disk, logging why it failed if it did
+fn load_image(path: &Path) -> Option<slint::Image> {
+    match slint::Image::load_from_path(path) {
+        Ok(image) => Some(image),
+        Err(e) => {
+            log::error!(
+                "could not load gauge image {}: {:?}, using the vector gauge",
+                path.display(),
+                e
+            );
+            None
+        }
+    }
+}
+
+/// exponential smoothing of what the gauges show, so needles move calmly
+/// purely cosmetic, the backend and anything it logs keep the raw samples
+pub struct DisplaySmoother {
+    time_constant: Duration,
+    shown: Option<Vec<f32>>,
+    latest: Vec<f32>,
+    last_update: Instant,
+}
+
+impl DisplaySmoother {
+    pub fn new(time_constant: Duration) -> Self {
+        DisplaySmoother {
+            time_constant,
+            shown: None,
+            latest: Vec::new(),
+            last_update: Instant::now(),
+        }
+    }
+
+    /// whether the shown values have caught up with the latest ones
+    pub fn settled(&self) -> bool {
+        match &self.shown {
+            Some(shown) => shown
+                .iter()
+                .zip(&self.latest)
+                .all(|(shown, latest)| (shown - latest).abs() < 0.5),
+            None => false,
+        }
+    }
+
+    /// make the next update() jump straight to the values it's given
+    pub fn reset(&mut self) {
+        self.shown = None;
+    }
+
+    /// move the shown values towards the latest ones and return them
+    /// time based, so it behaves the same however often the frontend updates
+    /// jumps straight to the latest values when the channel count changes
+    pub fn update(&mut self, latest: Vec<f32>) -> Vec<f32> {
+        let dt = self.last_update.elapsed();
+        self.last_update = Instant::now();
+
+        let shown = match self.shown.take() {
+            Some(mut shown) if !self.time_constant.is_zero() && shown.len() == latest.len() => {
+                let alpha = 1.0 - (-dt.as_secs_f32() / self.time_constant.as_secs_f32()).exp();
+                for (next, latest) in shown.iter_mut().zip(&latest) {
+                    *next += (latest - *next) * alpha;
+                }
+                shown
+            }
+            _ => latest.clone(),
+        };
+
+        self.latest = latest;
+        self.shown = Some(shown.clone());
+        shown
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// needle position for `value` on a dial spanning the whole ADC range
+    fn fraction(map: &NeedleMap, value: f32) -> f32 {
+        map.angle_fraction(value, (0.0, FULL_SCALE))
+    }
+
+    #[test]
+    fn table_interpolates_between_breakpoints() {
+        let map: NeedleMap = "0:0,1024:130,4096:260".parse().unwrap();
+        assert_eq!(fraction(&map, 0.0), 0.0);
+        assert_eq!(fraction(&map, 512.0), 0.25);
+        assert_eq!(fraction(&map, 1024.0), 0.5);
+        assert_eq!(fraction(&map, 2560.0), 0.75);
+        assert_eq!(fraction(&map, 4096.0), 1.0);
+    }
+
+    #[test]
+    fn table_clamps_to_its_end_breakpoints() {
+        let map: NeedleMap = "100:26,200:52".parse().unwrap();
+        assert_eq!(fraction(&map, 0.0), 0.1);
+        assert_eq!(fraction(&map, 100.0), 0.1);
+        assert_eq!(fraction(&map, 200.0), 0.2);
+        assert_eq!(fraction(&map, 4000.0), 0.2);
+    }
+
+    #[test]
+    fn unsorted_and_tiny_tables_still_place_the_needle() {
+        let unsorted: NeedleMap = "4096:260,0:0,1024:130".parse().unwrap();
+        assert_eq!(fraction(&unsorted, 512.0), 0.25);
+        assert_eq!(fraction(&unsorted, 2560.0), 0.75);
+
+        let single: NeedleMap = "100:130".parse().unwrap();
+        for value in [0.0, 100.0, 4096.0] {
+            assert_eq!(fraction(&single, value), 0.5);
+        }
+
+        assert_eq!(fraction(&NeedleMap::Table(Vec::new()), 2048.0), 0.0);
+    }
+
+    #[test]
+    fn smoother_eases_towards_a_step() {
+        let mut smoother = DisplaySmoother::new(Duration::from_secs(1));
+        assert_eq!(smoother.update(vec![0.0]), vec![0.0]);
+        std::thread::sleep(Duration::from_millis(10));
+        let eased = smoother.update(vec![100.0])[0];
+        assert!(eased > 0.0 && eased < 50.0, "eased to {}", eased);
+        assert!(!smoother.settled());
+
+        smoother.reset();
+        assert_eq!(smoother.update(vec![100.0]), vec![100.0]);
+        assert!(smoother.settled());
+
+        let mut unsmoothed = DisplaySmoother::new(Duration::ZERO);
+        unsmoothed.update(vec![0.0]);
+        assert_eq!(unsmoothed.update(vec![100.0]), vec![100.0]);
+    }
+
+    #[test]
+    fn smoother_converges_on_a_step() {
+        let mut smoother = DisplaySmoother::new(Duration::from_millis(10));
+        smoother.update(vec![0.0]);
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        let mut shown = 0.0;
+        loop {
+            std::thread::sleep(Duration::from_millis(5));
+            let next = smoother.update(vec![100.0])[0];
+            // never backs off or overshoots on the way
+            assert!(
+                next >= shown && next <= 100.0,
+                "went from {} to {}",
+                shown,
+                next
+            );
+            shown = next;
+            if smoother.settled() || Instant::now() > deadline {
+                break;
+            }
+        }
+        assert!(smoother.settled());
+        assert!((shown - 100.0).abs() < 0.5);
+    }
+
+    #[test]
+    fn zero_min_delta_redraws_on_any_change() {
+        let config = DisplayConfig::default();
+        assert!(config.moved_enough(&[100, 200], &[101, 200]));
+        assert!(!config.moved_enough(&[100, 200], &[100, 200]));
+        assert!(config.moved_enough(&[100, 200], &[100, 200, 300]));
+    }
+
+    #[test]
+    fn min_delta_holds_back_smaller_moves() {
+        let mut config = DisplayConfig::default();
+        config.min_deltas[0] = 10;
+        assert!(!config.moved_enough(&[100, 200], &[105, 200]));
+        assert!(!config.moved_enough(&[100, 200], &[91, 200]));
+        assert!(config.moved_enough(&[100, 200], &[110, 200]));
+        assert!(config.moved_enough(&[100, 200], &[90, 200]));
+        // a1 still redraws on any change
+        assert!(config.moved_enough(&[100, 200], &[105, 201]));
+    }
+
+    #[test]
+    fn manual_range_beats_auto_range_beats_full_scale() {
+        let mut config = DisplayConfig::default();
+        config.channels[0].full_scale = 2048.0;
+        assert_eq!(config.range(0, None), (0.0, 2048.0));
+        assert_eq!(config.range(0, Some((100.0, 900.0))), (100.0, 900.0));
+
+        config.ranges[0] = Some((500.0, 1500.0));
+        assert_eq!(config.range(0, Some((100.0, 900.0))), (500.0, 1500.0));
+        assert_eq!(config.range(0, None), (500.0, 1500.0));
+        // only the channel it was set for
+        assert_eq!(config.range(1, None), (0.0, FULL_SCALE));
+    }
+}
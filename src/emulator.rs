@@ -0,0 +1,269 @@
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::backend::{DeviceInfo, DEFAULT_CHANNELS};
+
+/// how often the emulator thread checks whether it should stop
+static STOP_CHECK_INTERVAL: Duration = Duration::from_millis(10);
+
+/// shape of the frames the emulator answers with, mirroring the backend's frame spec
+#[derive(Debug, Clone, Default)]
+pub struct FrameLayout {
+    /// append the status byte after the values
+    pub status_byte: bool,
+    /// capabilities sent back in the init acknowledgement, a bare `ack` if unset
+    pub device_info: Option<DeviceInfo>,
+    /// append a running sequence number after the status byte
+    pub sequence_byte: bool,
+    /// end the frame with the wrapping sum of its other bytes
+    pub checksum_byte: bool,
+    /// zero bytes appended to every frame, like firmware sending more than the viewer expects
+    pub padding: usize,
+    /// answer configuration commands with `ack`, like firmware that confirms them
+    pub command_acks: bool,
+}
+
+impl FrameLayout {
+    fn ack(&self) -> Vec<u8> {
+        match &self.device_info {
+            Some(info) => format!(
+                "ack:{}:{}:{}",
+                info.channels, info.bit_depth, info.status_byte as u8
+            )
+            .into_bytes(),
+            None => b"ack".to_vec(),
+        }
+    }
+
+    fn frame(&self, state: &mut DeviceState) -> Vec<u8> {
+        let mut frame: Vec<u8> = state.values.iter().flat_map(|v| v.to_be_bytes()).collect();
+        if self.status_byte {
+            frame.push(state.flags);
+        }
+        if self.sequence_byte {
+            frame.push(state.sequence);
+            state.sequence = state.sequence.wrapping_add(1);
+        }
+        if self.checksum_byte {
+            frame.push(frame.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)));
+        }
+        frame.resize(frame.len() + self.padding, 0);
+        frame
+    }
+}
+
+/// what the emulated device currently reads and has been asked
+#[derive(Debug)]
+struct DeviceState {
+    values: Vec<u16>,
+    flags: u8,
+    sequence: u8,
+    inits: u32,
+    polls: u32,
+    stops: u32,
+    /// `init` requests still to be ignored, as if lost on the way
+    lost_inits: u32,
+    /// polls go unanswered, the handshake still works
+    silent: bool,
+    /// sent in reply to every poll instead of a frame built from the layout
+    reply: Option<Vec<u8>>,
+    /// how long the device takes to answer a poll
+    reply_delay: Duration,
+    /// anything but init, poll, discover and stop, in the order it arrived
+    commands: Vec<Vec<u8>>,
+}
+
+impl Default for DeviceState {
+    /// the stock four channels, all reading 0
+    fn default() -> Self {
+        DeviceState {
+            values: vec![0; DEFAULT_CHANNELS],
+            flags: 0,
+            sequence: 0,
+            inits: 0,
+            polls: 0,
+            stops: 0,
+            lost_inits: 0,
+            silent: false,
+            reply: None,
+            reply_delay: Duration::ZERO,
+            commands: Vec::new(),
+        }
+    }
+}
+
+/// a device on a loopback socket that answers `init` and `poll` like the RP2040 does
+/// runs on its own thread until dropped
+pub struct EmulatedDevice {
+    addr: SocketAddr,
+    state: Arc<Mutex<DeviceState>>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl EmulatedDevice {
+    pub fn spawn(layout: FrameLayout) -> io::Result<Self> {
+        Self::spawn_at(SocketAddr::from((Ipv4Addr::LOCALHOST, 0)), layout)
+    }
+
+    /// listen on `addr` instead of an ephemeral IPv4 loopback port, e.g. on IPv6
+    pub fn spawn_at(addr: SocketAddr, layout: FrameLayout) -> io::Result<Self> {
+        let socket = UdpSocket::bind(addr)?;
+        socket.set_read_timeout(Some(STOP_CHECK_INTERVAL))?;
+        let addr = socket.local_addr()?;
+
+        let state = Arc::new(Mutex::new(DeviceState::default()));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let state = state.clone();
+            let stop = stop.clone();
+            thread::spawn(move || serve(socket, layout, state, stop))
+        };
+
+        Ok(EmulatedDevice {
+            addr,
+            state,
+            stop,
+            thread: Some(thread),
+        })
+    }
+
+    /// where the backend should send its requests
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// one value per channel, the frame grows or shrinks to match
+    pub fn set_values(&self, values: &[u16]) {
+        self.state.lock().unwrap().values = values.to_vec();
+    }
+
+    /// raw status byte, only sent if the layout has one
+    pub fn set_flags(&self, flags: u8) {
+        self.state.lock().unwrap().flags = flags;
+    }
+
+    /// number the next frame `sequence`, e.g. to replay an old one
+    pub fn set_sequence(&self, sequence: u8) {
+        self.state.lock().unwrap().sequence = sequence;
+    }
+
+    /// answer every poll with `reply` as is, e.g. a short or corrupted frame
+    pub fn set_reply(&self, reply: &[u8]) {
+        self.state.lock().unwrap().reply = Some(reply.to_vec());
+    }
+
+    /// stop or start answering polls, like a device that hung or was unplugged
+    pub fn set_silent(&self, silent: bool) {
+        self.state.lock().unwrap().silent = silent;
+    }
+
+    /// answer polls only after `delay`
+    pub fn set_reply_delay(&self, delay: Duration) {
+        self.state.lock().unwrap().reply_delay = delay;
+    }
+
+    /// ignore the next `count` init requests, like a lossy link dropping them
+    pub fn lose_inits(&self, count: u32) {
+        self.state.lock().unwrap().lost_inits = count;
+    }
+
+    /// `init` requests received so far, lost ones included
+    pub fn inits(&self) -> u32 {
+        self.state.lock().unwrap().inits
+    }
+
+    /// `poll` requests received so far
+    pub fn polls(&self) -> u32 {
+        self.state.lock().unwrap().polls
+    }
+
+    /// `stop` requests received so far
+    pub fn stops(&self) -> u32 {
+        self.state.lock().unwrap().stops
+    }
+
+    /// configuration commands received so far
+    pub fn commands(&self) -> Vec<Vec<u8>> {
+        self.state.lock().unwrap().commands.clone()
+    }
+}
+
+impl Drop for EmulatedDevice {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+fn serve(
+    socket: UdpSocket,
+    layout: FrameLayout,
+    state: Arc<Mutex<DeviceState>>,
+    stop: Arc<AtomicBool>,
+) {
+    let mut buf = [0u8; 64];
+
+    while !stop.load(Ordering::Relaxed) {
+        let (amt, from) = match socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                continue
+            }
+            Err(e) => panic!("emulated device socket failed: {:?}", e),
+        };
+
+        let (reply, delay) = {
+            let mut state = state.lock().unwrap();
+            match &buf[..amt] {
+                b"init" => {
+                    state.inits += 1;
+                    if state.lost_inits > 0 {
+                        state.lost_inits -= 1;
+                        continue;
+                    }
+                    (layout.ack(), Duration::ZERO)
+                }
+                b"poll" => {
+                    state.polls += 1;
+                    if state.silent {
+                        continue;
+                    }
+                    let reply = state.reply.clone();
+                    let frame = reply.unwrap_or_else(|| layout.frame(&mut state));
+                    (frame, state.reply_delay)
+                }
+                b"discover" => (
+                    socket.local_addr().unwrap().to_string().into_bytes(),
+                    Duration::ZERO,
+                ),
+                b"stop" => {
+                    state.stops += 1;
+                    continue;
+                }
+                command => {
+                    state.commands.push(command.to_vec());
+                    if !layout.command_acks {
+                        continue;
+                    }
+                    (b"ack".to_vec(), Duration::ZERO)
+                }
+            }
+        };
+
+        thread::sleep(delay);
+        let _ = socket.send_to(&reply, from);
+    }
+}
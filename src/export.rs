@@ -0,0 +1,474 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use serde::Serialize;
+
+use crate::backend::MAX_CHANNELS;
+use crate::source::AnalogSource;
+use crate::transport::Transport;
+
+/// how much a crash can lose from a recording
+static RECORDING_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+/// longest pause a replay keeps, before the speed multiplier
+/// longer gaps, like between two sessions appended to one file, are cut down to this
+static REPLAY_MAX_GAP: Duration = Duration::from_secs(1);
+
+/// how many samples an exporter writes, independent of how often the gauges redraw
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Decimation {
+    /// write every `n`th sample, 1 writes all of them
+    KeepEvery(u32),
+    /// write the average of all samples in each interval
+    Average(Duration),
+}
+
+impl Default for Decimation {
+    fn default() -> Self {
+        Decimation::KeepEvery(1)
+    }
+}
+
+/// `every:<n>` or `avg:<interval in ms>`
+impl FromStr for Decimation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (mode, amount) = s
+            .trim()
+            .split_once(':')
+            .ok_or(format!("decimation {:?} is not every:<n> or avg:<ms>", s))?;
+        let amount: u64 = amount
+            .trim()
+            .parse()
+            .map_err(|e| format!("invalid decimation amount {:?}: {:?}", amount, e))?;
+
+        if amount == 0 {
+            return Err(format!("decimation {:?} would never write anything", s));
+        }
+
+        match mode {
+            "every" => u32::try_from(amount)
+                .map(Decimation::KeepEvery)
+                .map_err(|_| format!("decimation {:?} is too large", s)),
+            "avg" => Ok(Decimation::Average(Duration::from_millis(amount))),
+            _ => Err(format!("unknown decimation mode {:?}", mode)),
+        }
+    }
+}
+
+impl Decimation {
+    /// read an exporter's decimation from `var`, keeping every sample if unset or invalid
+    pub fn from_env(var: &str) -> Self {
+        match std::env::var(var) {
+            Ok(spec) => spec.parse().unwrap_or_else(|e| {
+                log::warn!("ignoring {}: {}", var, e);
+                Decimation::default()
+            }),
+            Err(_) => Decimation::default(),
+        }
+    }
+}
+
+/// thins out the samples going to one exporter according to its `Decimation`
+pub struct Decimator {
+    mode: Decimation,
+    seen: u32,
+    sums: Vec<f64>,
+    count: u32,
+    window_start: Option<Instant>,
+}
+
+impl Decimator {
+    pub fn new(mode: Decimation) -> Self {
+        Decimator {
+            mode,
+            seen: 0,
+            sums: Vec::new(),
+            count: 0,
+            window_start: None,
+        }
+    }
+
+    /// feed one sample taken at `at`, returning what should be written, if anything
+    /// a change in the channel count starts a new averaging window
+    pub fn push(&mut self, at: Instant, values: &[u16]) -> Option<Vec<f64>> {
+        match self.mode {
+            Decimation::KeepEvery(n) => {
+                let keep = self.seen.is_multiple_of(n);
+                self.seen = self.seen.wrapping_add(1);
+                keep.then(|| values.iter().map(|&value| value as f64).collect())
+            }
+            Decimation::Average(interval) => {
+                if self.sums.len() != values.len() {
+                    self.sums = vec![0.0; values.len()];
+                    self.count = 0;
+                    self.window_start = None;
+                }
+
+                let window_start = *self.window_start.get_or_insert(at);
+                for (sum, &value) in self.sums.iter_mut().zip(values) {
+                    *sum += value as f64;
+                }
+                self.count += 1;
+
+                if at.duration_since(window_start) < interval {
+                    return None;
+                }
+
+                let count = self.count as f64;
+                let averages = self.sums.iter().map(|sum| sum / count).collect();
+                self.sums.fill(0.0);
+                self.count = 0;
+                self.window_start = None;
+                Some(averages)
+            }
+        }
+    }
+}
+
+/// appends readings to a CSV file, `timestamp_ms,a0,a1,...`
+pub struct CsvRecorder {
+    writer: BufWriter<File>,
+    decimator: Decimator,
+    last_flush: Instant,
+}
+
+impl CsvRecorder {
+    /// open `path` for appending, writing the header if the file is new or empty
+    pub fn create(path: &Path, channels: usize, decimation: Decimation) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        let empty = file.metadata()?.len() == 0;
+        let mut writer = BufWriter::new(file);
+
+        if empty {
+            write!(writer, "timestamp_ms")?;
+            for channel in 0..channels {
+                write!(writer, ",a{}", channel)?;
+            }
+            writeln!(writer)?;
+        }
+
+        Ok(CsvRecorder {
+            writer,
+            decimator: Decimator::new(decimation),
+            last_flush: Instant::now(),
+        })
+    }
+
+    /// record one sample taken `timestamp_ms` after the backend started, subject to decimation
+    pub fn record(&mut self, at: Instant, timestamp_ms: u128, values: &[u16]) -> io::Result<()> {
+        if let Some(values) = self.decimator.push(at, values) {
+            write!(self.writer, "{}", timestamp_ms)?;
+            for value in values {
+                write!(self.writer, ",{}", value)?;
+            }
+            writeln!(self.writer)?;
+        }
+
+        if self.last_flush.elapsed() >= RECORDING_FLUSH_INTERVAL {
+            self.writer.flush()?;
+            self.last_flush = Instant::now();
+        }
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// how a recording is played back
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ReplayConfig {
+    /// 2.0 plays twice as fast as recorded
+    pub speed: f32,
+    /// start over at the end instead of stopping
+    pub looping: bool,
+}
+
+impl Default for ReplayConfig {
+    fn default() -> Self {
+        ReplayConfig {
+            speed: 1.0,
+            looping: false,
+        }
+    }
+}
+
+impl ReplayConfig {
+    /// `REPLAY_SPEED` is the speed multiplier, `REPLAY_LOOP=1` loops
+    pub fn from_env() -> Self {
+        let mut config = ReplayConfig::default();
+
+        if let Ok(speed) = std::env::var("REPLAY_SPEED") {
+            match speed.trim().parse::<f32>() {
+                Ok(speed) if speed > 0.0 => config.speed = speed,
+                Ok(speed) => log::warn!("ignoring REPLAY_SPEED: {} is not positive", speed),
+                Err(e) => log::warn!("ignoring REPLAY_SPEED: {:?}", e),
+            }
+        }
+        config.looping = std::env::var_os("REPLAY_LOOP").is_some_and(|v| v == "1");
+
+        config
+    }
+}
+
+/// plays a CsvRecorder file back as if the device were sending it
+/// each poll gets the next row as a plain binary frame once it's due, averaged values are rounded
+/// when it's done and not looping every poll fails with `UnexpectedEof`, like an unplugged device
+pub struct CsvReplay {
+    config: ReplayConfig,
+    channels: usize,
+    rows: Vec<(u64, Vec<u16>)>, // timestamp_ms and values
+    next: usize,
+    due: Option<Instant>, // when the previous row was due, None before the first
+    last_request: Vec<u8>,
+}
+
+impl CsvReplay {
+    /// read the whole recording up front, so a bad row is reported before anything is shown
+    pub fn open(path: &Path, config: ReplayConfig) -> io::Result<Self> {
+        let contents = std::fs::read_to_string(path)?;
+        let invalid = |line: usize, reason: String| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}:{}: {}", path.display(), line + 1, reason),
+            )
+        };
+
+        let mut lines = contents.lines().enumerate();
+        let channels = match lines.next() {
+            Some((_, header)) if header.starts_with("timestamp_ms") => {
+                header.split(',').count() - 1
+            }
+            _ => Err(invalid(
+                0,
+                String::from("not a recording, no timestamp_ms header"),
+            ))?,
+        };
+        if channels > MAX_CHANNELS {
+            Err(invalid(
+                0,
+                format!(
+                    "{} channels, at most {} fit a frame",
+                    channels, MAX_CHANNELS
+                ),
+            ))?
+        }
+
+        let mut rows = Vec::new();
+        for (line, row) in lines.filter(|(_, row)| !row.trim().is_empty()) {
+            let mut fields = row.split(',').map(str::trim);
+            let timestamp_ms = fields
+                .next()
+                .unwrap_or_default()
+                .parse()
+                .map_err(|e| invalid(line, format!("invalid timestamp: {:?}", e)))?;
+            let values = fields
+                .map(|field| field.parse::<f64>().map(|value| value.round() as u16))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| invalid(line, format!("invalid value: {:?}", e)))?;
+            if values.len() != channels {
+                Err(invalid(
+                    line,
+                    format!("{} values, the header has {}", values.len(), channels),
+                ))?
+            }
+            rows.push((timestamp_ms, values));
+        }
+        if rows.is_empty() {
+            Err(invalid(0, String::from("no readings recorded")))?
+        }
+        log::info!("replaying {} readings from {}", rows.len(), path.display());
+
+        Ok(CsvReplay {
+            config,
+            channels,
+            rows,
+            next: 0,
+            due: None,
+            last_request: Vec::new(),
+        })
+    }
+
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+
+    /// how long after the previous row this one should be shown
+    fn gap(&self, row: usize) -> Duration {
+        let Some(previous) = row.checked_sub(1) else {
+            return Duration::ZERO;
+        };
+        // the timestamps restart where a second session was appended
+        let gap_ms = self.rows[row].0.saturating_sub(self.rows[previous].0);
+        Duration::from_millis(gap_ms)
+            .min(REPLAY_MAX_GAP)
+            .div_f32(self.config.speed)
+    }
+}
+
+impl Transport for CsvReplay {
+    fn send(&mut self, msg: &[u8]) -> io::Result<()> {
+        self.last_request = msg.to_vec();
+        Ok(())
+    }
+
+    /// answers `init` with a bare ack, everything else waits for the next row and returns it
+    /// a replay that falls behind, e.g. at high speeds, catches up rather than skipping rows
+    fn recv(&mut self, buf: &mut [u8], _frame_len: usize) -> io::Result<usize> {
+        if self.last_request == b"init" {
+            buf[..3].copy_from_slice(b"ack");
+            return Ok(3);
+        }
+
+        if self.next == self.rows.len() {
+            if !self.config.looping {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    "end of the recording",
+                ));
+            }
+            log::info!("end of the recording, starting over");
+            self.next = 0;
+        }
+
+        let due = match self.due {
+            Some(previous) => previous + self.gap(self.next),
+            None => Instant::now(),
+        };
+        if let Some(wait) = due.checked_duration_since(Instant::now()) {
+            thread::sleep(wait);
+        }
+        self.due = Some(due);
+
+        let (_, values) = &self.rows[self.next];
+        self.next += 1;
+        for (channel, value) in values.iter().enumerate() {
+            buf[channel * 2..channel * 2 + 2].copy_from_slice(&value.to_be_bytes());
+        }
+        Ok(values.len() * 2)
+    }
+
+    /// rows are waited for, never timed out on
+    fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        Ok(None)
+    }
+
+    fn set_read_timeout(&mut self, _timeout: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// the current reading and how the link is doing, for pasting into a bug report
+/// values are empty until the first poll succeeded
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Snapshot {
+    /// milliseconds since the unix epoch
+    pub taken_at_ms: u64,
+    pub connection: String,
+    pub raw: Vec<u16>,
+    /// in mV, through each channel's calibration
+    pub calibrated: Vec<f32>,
+    /// as each channel shows it, through its transform too
+    pub shown: Vec<f32>,
+    pub polls: u32,
+    pub timeouts: u32,
+    pub failures: u32,
+    pub out_of_order: u32,
+    /// successful polls per second
+    pub poll_rate: f64,
+}
+
+impl Snapshot {
+    /// only reads, so taking one doesn't disturb polling
+    pub fn of<S: AnalogSource + ?Sized>(be: &S) -> Self {
+        let stats = be.stats();
+        Snapshot {
+            taken_at_ms: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis() as u64,
+            connection: be.connection_state().to_string(),
+            raw: be
+                .read_raw()
+                .map_or_else(|_| Vec::new(), |vals| vals.channels.clone()),
+            calibrated: be.read_calibrated().unwrap_or_default(),
+            shown: be.read_display().unwrap_or_default(),
+            polls: stats.polls,
+            timeouts: stats.timeouts,
+            failures: stats.failures,
+            out_of_order: stats.out_of_order,
+            poll_rate: stats.rate(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        // nothing in a snapshot can fail to serialize, a NaN just comes out as null
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// write it as `snapshot-<taken_at_ms>.json` in `dir`, returning the file written
+    pub fn save_in(&self, dir: &Path) -> io::Result<PathBuf> {
+        let path = dir.join(format!("snapshot-{}.json", self.taken_at_ms));
+        std::fs::write(&path, self.to_json())?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::MockBackend;
+
+    #[test]
+    fn snapshot_has_the_latest_reading() {
+        let mut be = MockBackend::new();
+        assert!(Snapshot::of(&be).raw.is_empty());
+
+        be.poll().unwrap();
+        let snapshot = Snapshot::of(&be);
+        assert_eq!(snapshot.raw, be.read().unwrap().channels);
+        assert_eq!(snapshot.calibrated.len(), snapshot.raw.len());
+        assert_eq!(snapshot.polls, 1);
+
+        let json = snapshot.to_json();
+        assert!(json.contains("\"connection\": \"connected\""));
+        assert!(json.contains("\"raw\": ["));
+    }
+
+    #[test]
+    fn keep_every_writes_every_nth_sample() {
+        let mut decimator = Decimator::new(Decimation::KeepEvery(3));
+        let at = Instant::now();
+        let kept: Vec<Vec<f64>> = (0..8)
+            .filter_map(|i| decimator.push(at, &[i, 100 + i]))
+            .collect();
+        assert_eq!(kept, [[0.0, 100.0], [3.0, 103.0], [6.0, 106.0]]);
+    }
+
+    #[test]
+    fn average_writes_each_window_once() {
+        let mut decimator = Decimator::new(Decimation::Average(Duration::from_millis(100)));
+        let t0 = Instant::now();
+        let at = |ms| t0 + Duration::from_millis(ms);
+
+        assert_eq!(decimator.push(at(0), &[10, 0]), None);
+        assert_eq!(decimator.push(at(40), &[20, 0]), None);
+        assert_eq!(decimator.push(at(80), &[30, 0]), None);
+        assert_eq!(decimator.push(at(100), &[40, 4]), Some(vec![25.0, 1.0]));
+
+        // the next window starts over, nothing of the last one is carried into it
+        assert_eq!(decimator.push(at(150), &[100, 0]), None);
+        assert_eq!(decimator.push(at(260), &[200, 0]), Some(vec![150.0, 0.0]));
+
+        // and so does a change in the channel count
+        assert_eq!(decimator.push(at(300), &[1, 1]), None);
+        assert_eq!(decimator.push(at(320), &[7]), None);
+        assert_eq!(decimator.push(at(420), &[9]), Some(vec![8.0]));
+    }
+}
@@ -0,0 +1,78 @@
+use std::sync::{Arc, Mutex, PoisonError};
+
+/// a channel that holds one value, where the newest wins
+/// sending never waits for the receiver, a value it hasn't taken yet is overwritten
+pub fn channel<T>() -> (LatestSender<T>, LatestReceiver<T>) {
+    let slot = Arc::new(Mutex::new(None));
+    (LatestSender { slot: slot.clone() }, LatestReceiver { slot })
+}
+
+pub struct LatestSender<T> {
+    slot: Arc<Mutex<Option<T>>>,
+}
+
+impl<T> LatestSender<T> {
+    /// replace whatever is pending, true if the receiver never saw the value it replaced
+    /// the lock is only ever held to swap the value, never while it's being used
+    pub fn send(&self, value: T) -> bool {
+        let mut slot = self.slot.lock().unwrap_or_else(PoisonError::into_inner);
+        slot.replace(value).is_some()
+    }
+}
+
+pub struct LatestReceiver<T> {
+    slot: Arc<Mutex<Option<T>>>,
+}
+
+impl<T> LatestReceiver<T> {
+    /// the newest value sent since the last take, if any
+    pub fn take(&self) -> Option<T> {
+        self.slot
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn slow_receiver_never_holds_up_the_sender() {
+        let (sender, receiver) = channel();
+        let consumer = thread::spawn(move || {
+            let mut seen = Vec::new();
+            // a UI that spends far longer on each value than the sender takes to make one
+            while seen.last() != Some(&999) {
+                if let Some(value) = receiver.take() {
+                    seen.push(value);
+                }
+                thread::sleep(Duration::from_millis(50));
+            }
+            seen
+        });
+
+        let started = Instant::now();
+        let overwritten = (0..1000).filter(|&i| sender.send(i)).count();
+        assert!(started.elapsed() < Duration::from_millis(500));
+        assert!(overwritten > 0);
+
+        let seen = consumer.join().unwrap();
+        assert!(seen.len() < 1000);
+        assert!(seen.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(seen.last(), Some(&999));
+    }
+
+    #[test]
+    fn take_empties_the_slot() {
+        let (sender, receiver) = channel();
+        assert_eq!(receiver.take(), None);
+        assert!(!sender.send(1));
+        assert!(sender.send(2));
+        assert_eq!(receiver.take(), Some(2));
+        assert_eq!(receiver.take(), None);
+    }
+}
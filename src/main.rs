@@ -1,13 +1,45 @@
 use std::{
-    sync::{Arc, RwLock},
+    mem::Discriminant,
+    net::{Ipv4Addr, SocketAddr},
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex, RwLock,
+    },
     thread,
+    time::{Duration, Instant},
 };
 
-use backend::ViewerBackend;
-use slint::PlatformError;
+use backend::{
+    Calibration, ConnectionEvent, ConnectionState, DeviceFlags, DeviceInfo, DisplayTransform,
+    ExtraFields, PollStats, ProtocolMode, ViewerBackend, ViewerBackendConfig, ViewerBackendError,
+};
+use display::{DisplayConfig, DisplaySmoother, GaugeArtwork, VREF_MV};
+use export::{Decimation, ReplayConfig, Snapshot};
+use latest::{LatestReceiver, LatestSender};
+use settings::Settings;
+use simulation::{MockBackend, SimulationConfig};
+use slint::{Model, ModelRc, PlatformError, VecModel};
+use source::{read_source, write_source, AnalogSource};
+use theme::Theme;
 
 mod backend;
+mod decoder;
+mod display;
+#[cfg(test)]
+mod emulator;
+mod export;
+mod latest;
+mod metrics;
+mod settings;
+mod simulation;
+mod source;
+mod theme;
+mod transport;
 
+#[allow(dead_code)] // the payloads are only read through Debug when main() returns them
 #[derive(Debug)]
 enum AppError {
     GUIError(PlatformError),
@@ -15,162 +47,1773 @@ enum AppError {
 }
 
 slint::slint! {
-    export component Gauge inherits Image {
-        in property <int> value;
+    import { Button, CheckBox, Palette, Slider, SpinBox } from "std-widgets.slint";
+
+    export struct UiTheme {
+        has-background: bool,
+        background: color,
+        text: color,
+        font-family: string,
+        value-font-size: length,
+        detail-font-size: length,
+        gauge-size: length,
+        spacing: length,
+        dial: color,
+        needle: color,
+        hub: color,
+        reference: color,
+        alarm: color,
+        healthy: color,
+        inactive: color,
+    }
+
+    export enum ConnectionStatus { disconnected, connecting, connected, lost }
+
+    // set once from the display config, one per channel
+    export struct ChannelDisplay {
+        label: string,
+        unit: string,
+        // raw value at the end of the dial
+        full-scale: float,
+    }
+
+    export struct ChannelView {
+        value: int,
+        // value through the channel's calibration and transform, in its display unit
+        calibrated: float,
+        angle: float,
+        integrated: bool,
+        integral: float,
+        has-reference: bool,
+        reference-angle: float,
+        // how far above the reference target, in the display unit
+        deviation: float,
+        // value is forced by override_channel() rather than read from the device
+        overridden: bool,
+        // unticked channels only show their label, see set_channel_enabled()
+        enabled: bool,
+        // the reading was past full scale and is shown clamped to it
+        over-range: bool,
+        // outside its alarm thresholds
+        alarm: bool,
+        // displayed mean over the configured window
+        has-average: bool,
+        average: float,
+        // displayed peak hold, since connecting or the last reset
+        has-extremes: bool,
+        minimum: float,
+        maximum: float,
+        // path commands of the recent readings, in a 100 x 100 viewbox
+        sparkline: string,
+    }
+
+    // a button sending `command` to the devices
+    export struct CommandButton {
+        label: string,
+        command: string,
+    }
+
+    export struct DeviceView {
+        // shown above its gauges when there are several devices
+        name: string,
+        channels: [ChannelView],
+        // latest ADC counts as received, before the filter, overrides and smoothing
+        raw-values: [int],
+        connection-status: ConnectionStatus,
+        connection-text: string,
+        // ADC reference the uncalibrated channels convert against
+        vref-mv: int,
+    }
+
+    export struct DeviceStatus {
+        over-temp: bool,
+        low-battery: bool,
+        sensor-fault: bool,
+    }
+
+    component Indicator inherits HorizontalLayout {
+        in property <string> label;
+        in property <bool> lit;
+        in property <UiTheme> theme;
+
+        spacing: 8px;
+        Rectangle {
+            width: 16px;
+            height: 16px;
+            border-radius: self.width / 2;
+            background: lit ? theme.alarm : theme.inactive;
+        }
+        Text {
+            text: label;
+            color: theme.text;
+            font-family: theme.font-family;
+            font-size: theme.detail-font-size;
+            vertical-alignment: center;
+        }
+    }
 
-        Image {
-            source: @image-url("needle.png");
-            rotation-angle: ((value*1deg) / 4096deg) * 260deg;
-            height: 200px;
-            width: 200px;
+    // colored dot for how the connection is doing, then `label`
+    component ConnectionIndicator inherits HorizontalLayout {
+        in property <ConnectionStatus> status;
+        in property <string> label;
+        in property <UiTheme> theme;
+
+        spacing: 8px;
+        Rectangle {
+            width: 16px;
+            height: 16px;
+            border-radius: self.width / 2;
+            background: status == ConnectionStatus.connected ? theme.healthy
+                : status == ConnectionStatus.connecting ? theme.reference
+                : status == ConnectionStatus.lost ? theme.alarm
+                : theme.inactive;
         }
-        Image {
-            source: @image-url("gauge.png");
-            height: 200px;
-            width: 200px;
+        Text {
+            text: label;
+            color: theme.text;
+            font-family: theme.font-family;
+            font-size: theme.detail-font-size;
+            vertical-alignment: center;
         }
     }
-    export component App {
 
-        in property <int> a0;
-        in property <int> a1;
-        in property <int> a2;
-        in property <int> a3;
+    component Gauge inherits Rectangle {
+        // fraction of the needle sweep, computed by the needle map in Rust
+        in property <float> angle;
+        in property <image> face;
+        in property <image> needle;
+        // draw the dial without any image assets
+        in property <bool> vector;
+        in property <bool> has-reference;
+        in property <float> reference-angle;
+        in property <UiTheme> theme;
+
+        height: theme.gauge-size;
+        width: theme.gauge-size;
+
+        if !vector: Image {
+            source: needle;
+            transform-rotation: angle * 260deg;
+            height: parent.height;
+            width: parent.width;
+        }
+        if !vector: Image {
+            source: face;
+            height: parent.height;
+            width: parent.width;
+        }
+
+        if vector: Rectangle {
+            border-radius: self.width / 2;
+            border-width: 3px;
+            border-color: theme.dial;
+        }
+        if vector: Rectangle {
+            transform-rotation: angle * 260deg;
+
+            // pointing down at zero, like needle.png
+            Rectangle {
+                x: (parent.width - self.width) / 2;
+                y: parent.height / 2;
+                width: 6px;
+                height: parent.height * 0.4;
+                background: theme.needle;
+            }
+        }
+        if has-reference: Rectangle {
+            transform-rotation: reference-angle * 260deg;
+
+            // a short tick on the rim, at the same zero position as the needle
+            Rectangle {
+                x: (parent.width - self.width) / 2;
+                y: parent.height * 0.8;
+                width: 3px;
+                height: parent.height * 0.18;
+                background: theme.reference;
+            }
+        }
+        if vector: Rectangle {
+            width: 24px;
+            height: 24px;
+            border-radius: self.width / 2;
+            background: theme.hub;
+        }
+    }
+
+    export component App inherits Window {
+
+        // one row of gauges each, the status lines below follow the first
+        in property <[DeviceView]> devices;
+        in property <[ChannelDisplay]> channel-displays;
+        in property <bool> show-raw;
+        in property <image> face-image: @image-url("gauge.png");
+        in property <image> needle-image: @image-url("needle.png");
+        in property <bool> vector-gauge;
+        in property <bool> show-status;
+        in property <DeviceStatus> device-status;
+        // capabilities reported by the device, empty until it sends them
+        in property <string> device-info;
+        // successful polls per second and polls that produced nothing
+        in property <float> poll-rate;
+        in property <int> dropped-polls;
+        // mean and standard deviation of the time between samples, and how often it spiked
+        in property <float> interval-ms;
+        in property <float> jitter-ms;
+        in property <int> hitches;
+        in property <UiTheme> theme;
+        // formatted connection events, oldest first
+        in property <[string]> connection-history;
+        in property <ConnectionStatus> connection-status;
+        in property <[CommandButton]> commands;
+        in property <string> connection-text;
+        in-out property <bool> show-diagnostics;
+        in-out property <bool> recording;
+        // keep showing the current readings, the backend still polls and records underneath
+        in-out property <bool> frozen;
+        // EMA weight of each new sample, 1 shows the raw readings
+        in-out property <float> filter-alpha: 1;
+        in-out property <int> poll-delay-ms;
+        // switches between the light and the dark theme, the window's own widgets follow
+        in-out property <bool> dark-mode;
+        // every gauge's reading in raw ADC counts rather than its unit, the needles stay put
+        // the counts are raw-values, as the device sent them and not through the filter
+        in-out property <bool> show-counts;
+
+        background: theme.has-background ? theme.background : Palette.background;
 
         callback click_reconnect();
+        callback click_reset_integral(int, int);
+        callback toggle_channel(int, int, bool);
+        callback change_vref(int, int);
+        callback click_reset_peaks();
+        callback click_snapshot();
+        callback click_command(string);
+        callback toggle_recording(bool);
+        callback change_filter_alpha(float);
+        callback change_poll_delay(int);
+        callback toggle_dark_mode(bool);
+        callback toggle_counts(bool);
 
-        GridLayout {
-            spacing: 25px;
-            Row {
-                Gauge {
-                    value: a0;
+        changed dark-mode => {
+            Palette.color-scheme = dark-mode ? ColorScheme.dark : ColorScheme.light;
+        }
+
+        forward-focus: keys;
+
+        // space freezes and unfreezes the gauges, unless a control has focus and takes it itself
+        keys := FocusScope {
+            key-pressed(event) => {
+                if (event.text == " ") {
+                    frozen = !frozen;
+                    return accept;
                 }
-                Gauge {
-                    value: a1;
+                return reject;
+            }
+
+            VerticalLayout {
+                spacing: theme.spacing;
+                for device[device-index] in devices: VerticalLayout {
+                    spacing: theme.spacing;
+                    HorizontalLayout {
+                        alignment: center;
+                        spacing: theme.spacing;
+                        if devices.length > 1: ConnectionIndicator {
+                            theme: theme;
+                            status: device.connection-status;
+                            label: device.name + ", " + device.connection-text;
+                        }
+                        Text {
+                            text: "vref mV";
+                            font-family: theme.font-family;
+                            font-size: theme.detail-font-size;
+                            color: theme.text;
+                            vertical-alignment: center;
+                        }
+                        SpinBox {
+                            minimum: 1000;
+                            maximum: 5000;
+                            value: device.vref-mv;
+                            edited(mv) => { change_vref(device-index, mv); }
+                        }
+                    }
+                    HorizontalLayout {
+                        spacing: theme.spacing;
+                        for channel[index] in device.channels: VerticalLayout {
+                            spacing: theme.spacing;
+                            width: theme.gauge-size;
+                            HorizontalLayout {
+                                alignment: center;
+                                spacing: 5px;
+                                CheckBox {
+                                    checked: channel.enabled;
+                                    toggled => { toggle_channel(device-index, index, self.checked); }
+                                }
+                                Text {
+                                    text: channel-displays[index].label;
+                                    font-family: theme.font-family;
+                                    font-size: theme.detail-font-size;
+                                    color: theme.text;
+                                    vertical-alignment: center;
+                                }
+                            }
+                            // a disabled channel keeps its label and checkbox, the rest is hidden
+                            if !channel.enabled: Text {
+                                text: "disabled";
+                                font-family: theme.font-family;
+                                font-size: theme.detail-font-size;
+                                color: theme.inactive;
+                                horizontal-alignment: center;
+                                width: theme.gauge-size;
+                            }
+                            if channel.enabled: VerticalLayout {
+                                spacing: theme.spacing;
+                                Gauge {
+                                    theme: theme;
+                                    angle: channel.angle;
+                                    face: face-image;
+                                    needle: needle-image;
+                                    vector: vector-gauge;
+                                    has-reference: channel.has-reference;
+                                    reference-angle: channel.reference-angle;
+                                }
+                                if channel.sparkline != "": Path {
+                                    width: theme.gauge-size;
+                                    height: theme.gauge-size / 4;
+                                    viewbox-width: 100;
+                                    viewbox-height: 100;
+                                    commands: channel.sparkline;
+                                    stroke: theme.needle;
+                                    stroke-width: 1px;
+                                }
+                                Text {
+                                    text: show-counts ? device.raw-values[index] + " counts"
+                                        : (show-raw ? device.raw-values[index] + "/" + channel-displays[index].full-scale + " → " : "")
+                                            + round(channel.calibrated) + " " + channel-displays[index].unit;
+                                    font-family: theme.font-family;
+                                    font-size: theme.value-font-size;
+                                    color: channel.alarm ? theme.alarm : theme.text;
+                                    horizontal-alignment: center;
+                                    width: theme.gauge-size;
+                                }
+                                if channel.has-average: Text {
+                                    text: "avg " + round(channel.average) + " " + channel-displays[index].unit;
+                                    font-family: theme.font-family;
+                                    font-size: theme.detail-font-size;
+                                    color: theme.text;
+                                    horizontal-alignment: center;
+                                    width: theme.gauge-size;
+                                }
+                                if channel.has-extremes: Text {
+                                    text: "↓ " + round(channel.minimum) + "  ↑ " + round(channel.maximum);
+                                    font-family: theme.font-family;
+                                    font-size: theme.detail-font-size;
+                                    color: theme.text;
+                                    horizontal-alignment: center;
+                                    width: theme.gauge-size;
+                                }
+                                if channel.over-range: Text {
+                                    text: "OVER RANGE";
+                                    font-family: theme.font-family;
+                                    font-size: theme.detail-font-size;
+                                    color: theme.alarm;
+                                    horizontal-alignment: center;
+                                    width: theme.gauge-size;
+                                }
+                                if channel.overridden: Text {
+                                    text: "OVERRIDDEN";
+                                    font-family: theme.font-family;
+                                    font-size: theme.detail-font-size;
+                                    color: theme.alarm;
+                                    horizontal-alignment: center;
+                                    width: theme.gauge-size;
+                                }
+                                if channel.has-reference: Text {
+                                    text: "Δ " + (channel.deviation >= 0 ? "+" : "") + round(channel.deviation) + " " + channel-displays[index].unit;
+                                    font-family: theme.font-family;
+                                    font-size: theme.detail-font-size;
+                                    color: theme.reference;
+                                    horizontal-alignment: center;
+                                    width: theme.gauge-size;
+                                }
+                                if channel.integrated: HorizontalLayout {
+                                    spacing: 10px;
+                                    Text {
+                                        text: "∫ " + round(channel.integral) + " count·s";
+                                        font-family: theme.font-family;
+                                        font-size: theme.detail-font-size;
+                                        color: theme.text;
+                                        vertical-alignment: center;
+                                    }
+                                    Button {
+                                        text: "reset";
+                                        clicked => { click_reset_integral(device-index, index); }
+                                    }
+                                }
+                            }
+                        }
+                    }
                 }
-                Gauge {
-                    value: a2;
+                if device-info != "": Text {
+                    text: device-info;
+                    font-family: theme.font-family;
+                    font-size: theme.detail-font-size;
+                    color: theme.text;
+                    horizontal-alignment: center;
                 }
-                Gauge {
-                    value: a3;
+                ConnectionIndicator {
+                    alignment: center;
+                    theme: theme;
+                    status: connection-status;
+                    label: connection-text;
                 }
-            }
-            Row {
                 Text {
-                    text: round(a0 / 4096 * 3333) + " mV";
-                    font-size: 25px;
-                    color: blue;
+                    text: round(poll-rate) + " Hz, " + dropped-polls + " dropped, interval: "
+                        + interval-ms.to-fixed(1) + "ms ±" + jitter-ms.to-fixed(1) + "ms, " + hitches + " hitches";
+                    font-family: theme.font-family;
+                    font-size: theme.detail-font-size;
+                    color: theme.text;
                     horizontal-alignment: center;
-                    width: 200px;
                 }
-
-                Text {
-                    text: round(a1 / 4096 * 3333) + " mV";
-                    font-size: 25px;
-                    color: blue;
-                    horizontal-alignment: center;
-                    width: 200px;
+                if commands.length > 0: HorizontalLayout {
+                    alignment: center;
+                    spacing: theme.spacing;
+                    for button in commands: Button {
+                        text: button.label;
+                        clicked => { click_command(button.command); }
+                    }
                 }
+                HorizontalLayout {
+                    alignment: center;
+                    spacing: theme.spacing;
+                    Button {
+                        text: "reconnect";
+                        clicked => { click_reconnect(); }
+                    }
+                    Button {
+                        text: "reset peaks";
+                        clicked => { click_reset_peaks(); }
+                    }
+                    Button {
+                        text: "snapshot";
+                        clicked => { click_snapshot(); }
+                    }
+                    CheckBox {
+                        text: "record";
+                        checked <=> recording;
+                        toggled => { toggle_recording(self.checked); }
+                    }
+                    CheckBox {
+                        text: "freeze";
+                        checked <=> frozen;
+                    }
+                    CheckBox {
+                        text: "dark";
+                        checked <=> dark-mode;
+                        toggled => { toggle_dark_mode(self.checked); }
+                    }
+                    CheckBox {
+                        text: "counts";
+                        checked <=> show-counts;
+                        toggled => { toggle_counts(self.checked); }
+                    }
+                    Text {
+                        text: "filter " + round(filter-alpha * 100) / 100;
+                        font-family: theme.font-family;
+                        font-size: theme.detail-font-size;
+                        color: theme.text;
+                        vertical-alignment: center;
+                    }
+                    Slider {
+                        width: 120px;
+                        minimum: 0.01;
+                        maximum: 1;
+                        value <=> filter-alpha;
+                        changed(alpha) => { change_filter_alpha(alpha); }
+                    }
+                    Text {
+                        text: "poll ms";
+                        font-family: theme.font-family;
+                        font-size: theme.detail-font-size;
+                        color: theme.text;
+                        vertical-alignment: center;
+                    }
+                    SpinBox {
+                        minimum: 0;
+                        maximum: 1000;
+                        value <=> poll-delay-ms;
+                        edited(ms) => { change_poll_delay(ms); }
+                    }
+                    Button {
+                        text: show-diagnostics ? "hide diagnostics" : "diagnostics";
+                        clicked => { show-diagnostics = !show-diagnostics; }
+                    }
+                }
+                if show-diagnostics: VerticalLayout {
+                    for event in connection-history: Text {
+                        text: event;
+                        font-family: theme.font-family;
+                        font-size: theme.detail-font-size;
+                        color: theme.text;
+                    }
+                }
+                if show-status: HorizontalLayout {
+                    spacing: theme.spacing;
+                    alignment: center;
+                    Indicator {
+                        theme: theme;
+                        label: "over temp";
+                        lit: device-status.over-temp;
+                    }
+                    Indicator {
+                        theme: theme;
+                        label: "low battery";
+                        lit: device-status.low-battery;
+                    }
+                    Indicator {
+                        theme: theme;
+                        label: "sensor fault";
+                        lit: device-status.sensor-fault;
+                    }
+                }
+            }
+        }
+    }
+}
 
-                Text {
-                    text: round(a2 / 4096 * 3333) + " mV";
-                    font-size: 25px;
-                    color: blue;
-                    horizontal-alignment: center;
-                    width: 200px;
+/// how often the poll rate line is refreshed, it would flicker at the poll rate itself
+static STATS_REFRESH: Duration = Duration::from_millis(250);
+/// how often a poll thread looks for the reply it's waiting on, the backend is unlocked in between
+static REPLY_CHECK_INTERVAL: Duration = Duration::from_millis(1);
+/// how often the frontend checks for a fresh sample, about once per frame
+/// the poll threads publish no faster than this, the UI couldn't draw more anyway
+static UI_REFRESH: Duration = Duration::from_millis(16);
+/// most samples drawn in a sparkline, the history is thinned out to this
+static SPARKLINE_POINTS: usize = 200;
+/// the same error is logged at most this often, the rest are only counted
+static ERROR_LOG_INTERVAL: Duration = Duration::from_secs(1);
+
+/// logs a backend error that keeps happening, like every poll of an unplugged device, once per interval
+/// errors count as the same if they're the same variant
+struct ErrorThrottle {
+    what: &'static str,
+    last: Option<(Discriminant<ViewerBackendError>, Instant)>, // variant and when it was last logged
+    repeated: u32,                                             // held back since then
+}
+
+impl ErrorThrottle {
+    fn new(what: &'static str) -> Self {
+        ErrorThrottle {
+            what,
+            last: None,
+            repeated: 0,
+        }
+    }
+
+    fn error(&mut self, e: &ViewerBackendError) {
+        let kind = std::mem::discriminant(e);
+        match self.last {
+            Some((last, at)) if last == kind && at.elapsed() < ERROR_LOG_INTERVAL => {
+                self.repeated += 1;
+                return;
+            }
+            Some((last, _)) if last == kind => {
+                log::error!("{}: {:?} (repeated {} times)", self.what, e, self.repeated);
+                self.repeated = 0;
+            }
+            _ => {
+                self.clear();
+                log::error!("{}: {:?}", self.what, e);
+            }
+        }
+        self.last = Some((kind, Instant::now()));
+    }
+
+    /// the error stopped, say how often it happened since it was last logged
+    fn clear(&mut self) {
+        if self.last.take().is_some() && self.repeated > 0 {
+            log::info!("{}: repeated {} times", self.what, self.repeated);
+        }
+        self.repeated = 0;
+    }
+}
+
+/// a source shared between its poll thread and the frontend
+type SharedSource = Arc<RwLock<Box<dyn AnalogSource>>>;
+
+/// one of the watched devices, most runs have just the one
+#[derive(Clone)]
+struct Device {
+    /// what its row of gauges is labelled with, empty for a lone device
+    name: String,
+    backend: SharedSource,
+}
+
+impl Device {
+    /// run `f` on this device's ViewerBackend, if it has one
+    fn with_backend(&self, f: impl FnOnce(&mut ViewerBackend)) {
+        if let Some(be) = write_source(&self.backend).backend_mut() {
+            f(be)
+        }
+    }
+}
+
+/// everything the frontend shows, copied out of the backend so the lock isn't held while drawing
+struct FrontendState {
+    revision: u64,
+    values: Vec<u16>, // one per channel, the other per-channel fields match its length
+    raw: Vec<u16>,    // values before the filter, for reading off ADC counts
+    overrides: Vec<Option<u16>>,
+    integrals: Vec<f64>,
+    auto_ranges: Vec<Option<(f32, f32)>>,
+    calibrations: Vec<Calibration>,
+    transforms: Vec<DisplayTransform>,
+    alarms: Vec<bool>,
+    enabled: Vec<bool>,
+    extremes: Vec<Option<(u16, u16)>>,
+    averages: Vec<Option<f32>>, // raw, None unless the display asks for them
+    history: Vec<(f32, Vec<u16>)>, // age in seconds and values of recent readings, thinned out
+    status_byte: bool,
+    recording: bool, // a write error can end the recording behind the checkbox's back
+    flags: DeviceFlags,
+    device_info: Option<DeviceInfo>,
+}
+
+impl FrontendState {
+    fn read(
+        be: &dyn AnalogSource,
+        average_window: Option<Duration>,
+    ) -> Result<Self, ViewerBackendError> {
+        let vals = be.read()?;
+        let channels = 0..vals.len();
+        // a source without a device behind it shows plain readings
+        let device = be.backend();
+        let history = device.map_or(&[][..], |device| device.history());
+        let step = history.len().div_ceil(SPARKLINE_POINTS).max(1);
+
+        Ok(FrontendState {
+            revision: be.value_revision(),
+            values: vals.channels.clone(),
+            raw: be.read_raw()?.channels.clone(),
+            overrides: channels
+                .clone()
+                .map(|channel| device.and_then(|device| device.channel_override(channel)))
+                .collect(),
+            integrals: channels
+                .clone()
+                .map(|channel| device.map_or(0.0, |device| device.integral(channel)))
+                .collect(),
+            auto_ranges: channels
+                .clone()
+                .map(|channel| device.and_then(|device| device.auto_range(channel)))
+                .collect(),
+            calibrations: channels
+                .clone()
+                .map(|channel| {
+                    device.map_or_else(Calibration::default, |device| device.calibration(channel))
+                })
+                .collect(),
+            transforms: channels
+                .clone()
+                .map(|channel| {
+                    device.map_or(DisplayTransform::Linear, |device| device.transform(channel))
+                })
+                .collect(),
+            alarms: channels
+                .clone()
+                .map(|channel| device.is_some_and(|device| device.in_alarm(channel)))
+                .collect(),
+            enabled: channels
+                .clone()
+                .map(|channel| device.is_none_or(|device| device.channel_enabled(channel)))
+                .collect(),
+            extremes: channels
+                .clone()
+                .map(|channel| device.and_then(|device| device.extremes(channel)))
+                .collect(),
+            averages: channels
+                .map(|channel| {
+                    average_window
+                        .zip(device)
+                        .map(|(window, device)| device.window_average(channel, window))
+                })
+                .collect(),
+            history: history
+                .iter()
+                .step_by(step)
+                .map(|(at, values)| (at.elapsed().as_secs_f32(), values.channels.clone()))
+                .collect(),
+            status_byte: device.is_some_and(|device| device.status_byte()),
+            recording: device.is_some_and(|device| device.is_recording()),
+            flags: device
+                .map(|device| device.device_flags())
+                .unwrap_or_default(),
+            device_info: device.and_then(|device| device.device_info()).cloned(),
+        })
+    }
+}
+
+/// what a poll thread hands the frontend, so drawing never holds a lock the poll thread waits on
+struct Sample {
+    connection: ConnectionState,
+    connection_history: Vec<ConnectionEvent>,
+    stats: PollStats,
+    state: Result<FrontendState, ViewerBackendError>,
+}
+
+impl Sample {
+    fn read(be: &dyn AnalogSource, average_window: Option<Duration>) -> Self {
+        Sample {
+            connection: be.connection_state(),
+            connection_history: be.connection_history(),
+            stats: be.stats(),
+            state: FrontendState::read(be, average_window),
+        }
+    }
+}
+
+/// the gauges of one device as the frontend shows them, smoothed and clamped to the dial
+fn channel_views(
+    state: &FrontendState,
+    display_config: &DisplayConfig,
+    smoother: &mut DisplaySmoother,
+    history_window: Option<Duration>,
+) -> Vec<ChannelView> {
+    // raw_values and the logs keep the true readings, the gauges stop at full scale
+    let (clamped, over_range): (Vec<f32>, Vec<bool>) = (0..state.values.len())
+        .map(|channel| {
+            let raw = state.overrides[channel].unwrap_or(state.values[channel]);
+            display_config.channels[channel].clamp(raw as f32)
+        })
+        .unzip();
+    let shown = smoother.update(clamped);
+
+    (0..state.values.len())
+        .map(|channel| {
+            let map = &display_config.needle_maps[channel];
+            let reference = display_config.references[channel];
+            let value = shown[channel];
+            let calibration = state.calibrations[channel];
+            let transform = state.transforms[channel];
+            let shown_value = |raw: f32| transform.apply(calibration.to_mv(raw));
+            // the temperature transform falls as the voltage rises, so the raw extremes can swap
+            let extremes = state.extremes[channel].map(|(min, max)| {
+                let (min, max) = (shown_value(min as f32), shown_value(max as f32));
+                (min.min(max), min.max(max))
+            });
+            let range = display_config.range(channel, state.auto_ranges[channel]);
+            ChannelView {
+                value: value.round() as i32,
+                calibrated: shown_value(value),
+                angle: map.angle_fraction(value, range),
+                integrated: display_config.integrated[channel],
+                integral: state.integrals[channel] as f32,
+                has_reference: reference.is_some(),
+                reference_angle: reference.map_or(0.0, |target| {
+                    map.angle_fraction(calibration.to_raw(transform.invert(target)), range)
+                }),
+                deviation: reference.map_or(0.0, |target| shown_value(value) - target),
+                overridden: state.overrides[channel].is_some(),
+                enabled: state.enabled[channel],
+                over_range: over_range[channel] && state.enabled[channel],
+                alarm: state.alarms[channel],
+                has_average: state.averages[channel].is_some(),
+                average: state.averages[channel].map_or(0.0, shown_value),
+                has_extremes: extremes.is_some(),
+                minimum: extremes.map_or(0.0, |(min, _)| min),
+                maximum: extremes.map_or(0.0, |(_, max)| max),
+                sparkline: display::sparkline(
+                    state.history.iter().filter_map(|(age, values)| {
+                        values.get(channel).map(|&value| (*age, value as f32))
+                    }),
+                    history_window.unwrap_or(backend::HISTORY_WINDOW),
+                    range,
+                )
+                .into(),
+            }
+        })
+        .collect()
+}
+
+fn connection_status(state: ConnectionState) -> ConnectionStatus {
+    match state {
+        ConnectionState::Disconnected => ConnectionStatus::Disconnected,
+        ConnectionState::Connecting => ConnectionStatus::Connecting,
+        ConnectionState::Connected => ConnectionStatus::Connected,
+        ConnectionState::Lost => ConnectionStatus::Lost,
+    }
+}
+
+/// the theme as the frontend's properties want it
+fn ui_theme(theme: &Theme) -> UiTheme {
+    UiTheme {
+        has_background: theme.background.is_some(),
+        background: theme.background.map(|c| c.0).unwrap_or_default(),
+        text: theme.text.0,
+        font_family: theme.font_family.as_str().into(),
+        value_font_size: theme.value_font_size,
+        detail_font_size: theme.detail_font_size,
+        gauge_size: theme.gauge_size,
+        spacing: theme.spacing,
+        dial: theme.dial.0,
+        needle: theme.needle.0,
+        hub: theme.hub.0,
+        reference: theme.reference.0,
+        alarm: theme.alarm.0,
+        healthy: theme.healthy.0,
+        inactive: theme.inactive.0,
+    }
+}
+
+/// read a setting from the environment, warning and using the default if it doesn't parse
+fn env_or<T: std::str::FromStr>(var: &str, default: T) -> T
+where
+    T::Err: std::fmt::Debug,
+{
+    match std::env::var(var) {
+        Ok(value) => value.trim().parse().unwrap_or_else(|e| {
+            log::warn!("ignoring {}: {:?}", var, e);
+            default
+        }),
+        Err(_) => default,
+    }
+}
+
+/// device addresses and poll delay, `LOCAL_ADDR` / `REMOTE_ADDR` take `ip:port`
+/// `LOCAL_PORT` / `REMOTE_PORT` change just the port, `POLL_DELAY_MS` the minimum time between polls
+/// `REMOTE_ADDR` can be a host name too, e.g. `rp2040.local:4000`, and either address IPv6, e.g. `[fe80::1%2]:4000`
+/// `READ_TIMEOUT_MS` is how long a poll waits for the reply, 0 waits forever
+/// anything not in the environment comes from the saved settings, then the defaults
+fn backend_config(settings: &Settings) -> ViewerBackendConfig {
+    let defaults = ViewerBackendConfig::default();
+
+    let mut local_addr: SocketAddr = env_or(
+        "LOCAL_ADDR",
+        settings.local_addr.unwrap_or(defaults.local_addr),
+    );
+    local_addr.set_port(env_or("LOCAL_PORT", local_addr.port()));
+    let remote_default = settings.remote_addr.unwrap_or(defaults.remote_addr);
+    let mut remote_addr = match std::env::var("REMOTE_ADDR") {
+        Ok(target) => backend::resolve_addr(&target).unwrap_or_else(|e| {
+            log::warn!("ignoring REMOTE_ADDR: {:?}", e);
+            remote_default
+        }),
+        Err(_) => remote_default,
+    };
+    remote_addr.set_port(env_or("REMOTE_PORT", remote_addr.port()));
+
+    ViewerBackendConfig {
+        local_addr,
+        remote_addr,
+        poll_delay: Duration::from_millis(env_or(
+            "POLL_DELAY_MS",
+            settings
+                .poll_delay_ms
+                .unwrap_or(defaults.poll_delay.as_millis() as u64),
+        )),
+        read_timeout: Some(Duration::from_millis(env_or(
+            "READ_TIMEOUT_MS",
+            defaults
+                .read_timeout
+                .map_or(0, |timeout| timeout.as_millis() as u64),
+        )))
+        .filter(|timeout| !timeout.is_zero()),
+    }
+}
+
+/// how often the poll thread logs that it is still alive, from `HEARTBEAT_SECS`
+/// defaults to a minute, 0 turns the heartbeat off
+fn heartbeat_interval() -> Option<Duration> {
+    match env_or("HEARTBEAT_SECS", 60) {
+        0 => None,
+        secs => Some(Duration::from_secs(secs)),
+    }
+}
+
+/// set `shutdown` on Ctrl-C or SIGTERM so the socket is let go of cleanly instead of dying mid-poll
+#[cfg(unix)]
+fn shut_down_on_signals(shutdown: &Arc<AtomicBool>) {
+    for signal in [libc::SIGINT, libc::SIGTERM] {
+        let shutdown = shutdown.clone();
+        // the handler only does an atomic store, which is async-signal-safe
+        let registered = unsafe {
+            signal_hook_registry::register(signal, move || shutdown.store(true, Ordering::Relaxed))
+        };
+        if let Err(e) = registered {
+            log::warn!("can't handle signal {}: {:?}", signal, e);
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn shut_down_on_signals(_shutdown: &Arc<AtomicBool>) {}
+
+/// `--metrics-port <port>` serves Prometheus metrics there, nothing is served without it
+fn metrics_port() -> Option<u16> {
+    let port = std::env::args()
+        .skip_while(|arg| arg != "--metrics-port")
+        .nth(1)?;
+    port.parse()
+        .map_err(|e| log::warn!("ignoring --metrics-port {:?}: {:?}", port, e))
+        .ok()
+}
+
+/// `COMMANDS` adds buttons that send configuration commands to the devices
+/// `label=command` separated by `;`, e.g. `set gain 2x=gain:2;1 kHz=rate:1000`, a bare command is its own label
+fn command_buttons() -> Vec<CommandButton> {
+    let Ok(commands) = std::env::var("COMMANDS") else {
+        return Vec::new();
+    };
+    commands
+        .split(';')
+        .map(str::trim)
+        .filter(|button| !button.is_empty())
+        .map(|button| {
+            let (label, command) = button.split_once('=').unwrap_or((button, button));
+            CommandButton {
+                label: label.trim().into(),
+                command: command.trim().into(),
+            }
+        })
+        .collect()
+}
+
+/// how often headless mode prints the readings, from `HEADLESS_INTERVAL_MS`
+fn headless_interval() -> Duration {
+    Duration::from_millis(env_or("HEADLESS_INTERVAL_MS", 1000))
+}
+
+/// print the latest readings to stdout every `interval` until `shutdown` is set, instead of the window
+/// a line per device, each starting with its name if there are several
+fn print_readings(devices: &[Device], shutdown: &AtomicBool, interval: Duration) {
+    let mut last_print = Instant::now();
+    while !shutdown.load(Ordering::Relaxed) {
+        // short naps so Ctrl-C doesn't wait out a long interval
+        thread::sleep(interval.min(STATS_REFRESH));
+        if last_print.elapsed() < interval {
+            continue;
+        }
+        last_print = Instant::now();
+
+        for device in devices {
+            match read_source(&device.backend).read_raw() {
+                Ok(vals) if devices.len() > 1 => println!("{}: {}", device.name, vals),
+                Ok(vals) => println!("{}", vals),
+                Err(e) => log::error!("error reading backend: {:?}", e),
+            }
+        }
+    }
+}
+
+/// stop the poll threads, then tell the devices we're going
+fn shut_down(devices: &[Device], shutdown: &AtomicBool, pollers: Vec<thread::JoinHandle<()>>) {
+    log::info!("shutting down");
+    shutdown.store(true, Ordering::Relaxed);
+    for poller in pollers {
+        if poller.join().is_err() {
+            log::error!("a backend thread panicked");
+        }
+    }
+    for_each_source(devices, |be| {
+        if let Err(e) = be.shutdown() {
+            log::error!("error stopping device: {:?}", e);
+        }
+    });
+}
+
+/// run `f` on every device's backend
+fn for_each_source(devices: &[Device], mut f: impl FnMut(&mut Box<dyn AnalogSource>)) {
+    for device in devices {
+        f(&mut write_source(&device.backend));
+    }
+}
+
+/// run `f` on the ViewerBackend of every device that has one, for settings a made up source lacks
+fn for_each_backend(devices: &[Device], mut f: impl FnMut(&mut ViewerBackend)) {
+    for_each_source(devices, |source| {
+        if let Some(be) = source.backend_mut() {
+            f(be)
+        }
+    });
+}
+
+/// poll `backend` on a thread of its own until `stop` is set
+/// after a poll the frontend is handed a sample, at most once per UI refresh
+/// a sample it hasn't taken yet is replaced, so a slow frontend only ever costs it frames
+/// the backend is only locked to send the request and to check for the reply, never while waiting on it
+/// a poll that panics is caught and the link started over, rather than ending the thread
+fn spawn_poller(
+    backend: SharedSource,
+    stop: Arc<AtomicBool>,
+    heartbeat_interval: Option<Duration>,
+    changes: Arc<AtomicU64>,
+    samples: LatestSender<Sample>,
+    average_window: Option<Duration>,
+) -> thread::JoinHandle<()> {
+    thread::spawn(move || {
+        log::info!("backend thread started");
+
+        let mut last_heartbeat = Instant::now();
+        let mut last_sample: Option<Instant> = None;
+        let mut skipped_samples = 0u64;
+        let mut poll_errors = ErrorThrottle::new("error polling backend");
+        // a request is out and its reply hasn't been read yet
+        let mut waiting = false;
+
+        while !stop.load(Ordering::Relaxed) {
+            // sleep through the poll delay rather than spinning on the lock until it passes
+            let wait = if waiting {
+                REPLY_CHECK_INTERVAL
+            } else {
+                read_source(&backend).next_poll_in()
+            };
+            if !wait.is_zero() {
+                thread::sleep(wait);
+            }
+
+            let mut wl = write_source(&backend);
+            // caught before the guard drops, so the lock isn't poisoned for everyone else either
+            waiting = match panic::catch_unwind(AssertUnwindSafe(|| {
+                wl.try_poll().map(|vals| vals.is_none())
+            })) {
+                Ok(Ok(false)) => {
+                    poll_errors.clear();
+                    false
+                }
+                // nothing new is also what a poll that isn't due yet gives, that one's slept through instead
+                Ok(Ok(true)) => wl.next_poll_in().is_zero(),
+                Ok(Err(e)) => {
+                    poll_errors.error(&e);
+                    false
+                }
+                // it may have been half way through a request, start the link over
+                Err(_) => {
+                    log::error!("poll panicked, reconnecting");
+                    if let Err(e) = wl.reconnect() {
+                        poll_errors.error(&e);
+                    }
+                    false
+                }
+            };
+            if !waiting && last_sample.is_none_or(|at| at.elapsed() >= UI_REFRESH) {
+                last_sample = Some(Instant::now());
+                if samples.send(Sample::read(wl.as_ref(), average_window)) {
+                    skipped_samples += 1;
                 }
+            }
+            drop(wl);
 
-                Text {
-                    text: round(a3 / 4096 * 3333) + " mV";
-                    font-size: 25px;
-                    color: blue;
-                    horizontal-alignment: center;
-                    width: 200px;
+            if let Some(interval) = heartbeat_interval {
+                if last_heartbeat.elapsed() >= interval {
+                    let be = read_source(&backend);
+                    log::info!(
+                        "still alive, last values {:?}, rate {:.1} Hz, drops {}, changes {}, samples the UI skipped {}",
+                        be.read_display().ok(),
+                        be.stats().rate(),
+                        be.stats().dropped(),
+                        changes.load(Ordering::Relaxed),
+                        skipped_samples
+                    );
+                    last_heartbeat = Instant::now();
                 }
             }
         }
+    })
+}
+
+/// `REMOTE_ADDRS` watches several devices at once, `ip:port` or `host:port` separated by commas
+/// each gets a socket of its own, on the configured local port plus its place in the list
+/// a local port of 0 gives each an ephemeral port instead
+fn connect_all(
+    base: &ViewerBackendConfig,
+    addrs: &str,
+) -> Result<Vec<(String, ViewerBackend)>, ViewerBackendError> {
+    let mut devices = Vec::new();
+    let addrs = addrs
+        .split(',')
+        .map(str::trim)
+        .filter(|addr| !addr.is_empty());
+    for (i, addr) in addrs.enumerate() {
+        let mut local_addr = base.local_addr;
+        if local_addr.port() != 0 {
+            let port = local_addr.port().checked_add(i as u16).ok_or_else(|| {
+                ViewerBackendError::ParserError(format!("no local port left for {}", addr))
+            })?;
+            local_addr.set_port(port);
+        }
+
+        let config = ViewerBackendConfig {
+            local_addr,
+            remote_addr: backend::resolve_addr(addr)?,
+            ..base.clone()
+        };
+        devices.push((addr.to_string(), ViewerBackend::connect_with(config)?));
+    }
+
+    if devices.is_empty() {
+        Err(ViewerBackendError::ParserError(String::from(
+            "REMOTE_ADDRS lists no devices",
+        )))?
     }
+    Ok(devices)
 }
 
 fn main() -> Result<(), AppError> {
-    simple_logger::SimpleLogger::new().env().init().unwrap();
+    // `--headless` prints readings instead of opening a window, for machines without a display
+    // stdout is for the readings there, so only warnings are logged unless RUST_LOG says otherwise
+    let headless = std::env::args().skip(1).any(|arg| arg == "--headless");
+    let log_level = if headless {
+        log::LevelFilter::Warn
+    } else {
+        log::LevelFilter::Trace
+    };
+    simple_logger::SimpleLogger::new()
+        .with_level(log_level)
+        .env()
+        .init()
+        .unwrap();
+
+    let settings = Settings::load_or_default();
+    let mut display_config = DisplayConfig::from_env();
+    settings.apply_display(&mut display_config);
+    let status_byte = std::env::var_os("STATUS_BYTE").is_some_and(|v| v == "1");
+    let init_attempts = env_or("INIT_ATTEMPTS", backend::INIT_ATTEMPTS);
+    let backend_config = backend_config(&settings);
+    // 0 for no timeout
+    let frame_timeout = Some(Duration::from_millis(env_or("FRAME_TIMEOUT_MS", 0)))
+        .filter(|timeout| !timeout.is_zero());
+    if frame_timeout.is_some() && backend_config.read_timeout.is_none() {
+        log::warn!("FRAME_TIMEOUT_MS has no effect while receives block, set READ_TIMEOUT_MS too");
+    }
+
+    // 0 trusts the device to wait for polls however long they take
+    let stall_timeout = Some(Duration::from_millis(env_or(
+        "STALL_TIMEOUT_MS",
+        backend::STALL_TIMEOUT.as_millis() as u64,
+    )))
+    .filter(|timeout| !timeout.is_zero());
+
+    let heartbeat_interval = heartbeat_interval();
+    // `DISCOVER_MS` looks for the device for that long instead of using the configured address
+    let discover_timeout =
+        std::env::var_os("DISCOVER_MS").map(|_| Duration::from_millis(env_or("DISCOVER_MS", 1000)));
+    // 0 keeps no history and hides the sparklines
+    let history_window = Some(Duration::from_secs(env_or(
+        "HISTORY_SECS",
+        backend::HISTORY_WINDOW.as_secs(),
+    )))
+    .filter(|window| !window.is_zero());
+    // frames that actually changed something, for the heartbeat
+    let changes = Arc::new(AtomicU64::new(0));
+
+    let sources: Vec<(String, Box<dyn AnalogSource>)> = if std::env::var_os("MOCK_BACKEND")
+        .is_some_and(|v| v == "1")
+    {
+        vec![(String::new(), Box::new(MockBackend::new()))]
+    } else {
+        // `REPLAY_PATH` plays back a recording instead, see ReplayConfig::from_env() for how
+        let replay_path = std::env::var_os("REPLAY_PATH");
+        let simulate = std::env::var_os("SIMULATE").is_some_and(|v| v == "1");
+        let connected = if let Ok(addrs) = std::env::var("REMOTE_ADDRS") {
+            connect_all(&backend_config, &addrs)
+        } else {
+            let connected = if simulate {
+                Ok(ViewerBackend::simulate(SimulationConfig::from_env()))
+            } else if let Some(path) = &replay_path {
+                ViewerBackend::replay(Path::new(path), ReplayConfig::from_env())
+            } else if let Some(path) = std::env::var_os("FIFO_PATH") {
+                ViewerBackend::connect_fifo(Path::new(&path))
+            } else if let Some(timeout) = discover_timeout {
+                // the device may be on any network, so don't insist on the default local ip
+                let local_addr = match std::env::var_os("LOCAL_ADDR") {
+                    Some(_) => backend_config.local_addr,
+                    None => {
+                        SocketAddr::from((Ipv4Addr::UNSPECIFIED, backend_config.local_addr.port()))
+                    }
+                };
+                ViewerBackend::discover(timeout).and_then(|remote_addr| {
+                    ViewerBackend::connect_with(ViewerBackendConfig {
+                        local_addr,
+                        remote_addr,
+                        ..backend_config
+                    })
+                })
+            } else if [
+                "LOCAL_ADDR",
+                "REMOTE_ADDR",
+                "LOCAL_PORT",
+                "REMOTE_PORT",
+                "POLL_DELAY_MS",
+                "READ_TIMEOUT_MS",
+            ]
+            .iter()
+            .any(|var| std::env::var_os(var).is_some())
+                || settings.local_addr.is_some()
+                || settings.remote_addr.is_some()
+                || settings.poll_delay_ms.is_some()
+            {
+                ViewerBackend::connect_with(backend_config)
+            } else {
+                ViewerBackend::connect()
+            };
+            connected.map(|be| vec![(String::new(), be)])
+        };
+        let connected = connected.map_err(AppError::BackendError)?;
+
+        let configure = |name: &str, be: &mut ViewerBackend| {
+            for (channel, &enabled) in display_config.integrated.iter().enumerate() {
+                be.set_integral_enabled(channel, enabled);
+            }
+            // a replay makes its own frames, the device's frame format doesn't apply
+            // nor to the simulation, it only sends bare binary samples, however many channels are asked for
+            if replay_path.is_none() && !simulate {
+                be.set_protocol(
+                    env_or("PROTOCOL", ProtocolMode::default()),
+                    env_or("TEXT_EXTRA_FIELDS", ExtraFields::default()),
+                );
+                be.set_status_byte(status_byte);
+                be.set_sequence_byte(std::env::var_os("SEQUENCE_BYTE").is_some_and(|v| v == "1"));
+                be.set_checksum_byte(std::env::var_os("CHECKSUM_BYTE").is_some_and(|v| v == "1"));
+            }
+            if replay_path.is_none() {
+                be.set_channels(env_or("CHANNELS", backend::DEFAULT_CHANNELS));
+            }
+            be.set_recording_decimation(Decimation::from_env("RECORDING_DECIMATION"));
+            be.set_init_attempts(init_attempts);
+            be.set_frame_timeout(frame_timeout);
+            be.set_command_acks(std::env::var_os("COMMAND_ACK").is_some_and(|v| v == "1"));
+            be.set_stall_timeout(stall_timeout);
+            be.set_history_window(history_window);
+            be.set_auto_range(display_config.auto_range_rate);
+            be.set_fragmentation_threshold(env_or(
+                "FRAGMENTATION_THRESHOLD",
+                backend::FRAGMENTATION_THRESHOLD,
+            ));
+            be.check_frame_size();
+            for (channel, &value) in display_config.overrides.iter().enumerate() {
+                be.override_channel(channel, value);
+            }
+            for channel in 0..backend::MAX_CHANNELS {
+                let var = format!("CALIBRATION_A{}", channel);
+                be.set_calibration(channel, env_or(&var, settings.calibration(channel)));
+                let var = format!("TRANSFORM_A{}", channel);
+                be.set_transform(channel, env_or(&var, settings.transform(channel)));
+                let var = format!("FILTER_ALPHA_A{}", channel);
+                be.set_filter_alpha(channel, env_or(&var, settings.filter_alpha.unwrap_or(1.0)));
+                let var = format!("ENABLED_A{}", channel);
+                be.set_channel_enabled(
+                    channel,
+                    env_or(&var, settings.enabled(name, channel) as u8) != 0,
+                );
+                let var = format!("THRESHOLDS_A{}", channel);
+                be.set_thresholds(channel, env_or(&var, settings.thresholds(channel)));
+            }
+            // after the calibrations, so only the channels left on the ideal one follow it
+            let saved = settings.device(name).and_then(|device| device.vref_mv);
+            let vref_mv = env_or("VREF_MV", saved.unwrap_or(display::VREF_MV));
+            if vref_mv > 0.0 {
+                be.set_vref_mv(vref_mv);
+            } else {
+                log::warn!("VREF_MV must be positive, keeping {} mV", be.vref_mv());
+            }
+
+            let changes = changes.clone();
+            be.on_values_changed(Box::new(move |_| {
+                changes.fetch_add(1, Ordering::Relaxed);
+            }));
+        };
+
+        connected
+            .into_iter()
+            .map(|(name, mut be)| {
+                configure(&name, &mut be);
+                (name, Box::new(be) as Box<dyn AnalogSource>)
+            })
+            .collect()
+    };
+
+    let devices: Vec<Device> = sources
+        .into_iter()
+        .map(|(name, source)| Device {
+            name,
+            backend: Arc::new(RwLock::new(source)),
+        })
+        .collect();
+    // the status lines, metrics, recording and device info follow the first device
+    let backend = devices[0].backend.clone();
 
-    let backend = Arc::new(RwLock::new(
-        ViewerBackend::connect().map_err(|e| AppError::BackendError(e))?,
+    for device in &devices {
+        write_source(&device.backend)
+            .connect_socket()
+            .map_err(AppError::BackendError)?;
+    }
+
+    // set when the window closes or on Ctrl-C, the poll threads and the UI check it
+    let shutdown = Arc::new(AtomicBool::new(false));
+    shut_down_on_signals(&shutdown);
+
+    // handle updates offthread, one thread per device so a silent one doesn't hold up the others
+    let mut samples: Vec<LatestReceiver<Sample>> = Vec::new();
+    let pollers: Vec<thread::JoinHandle<()>> = devices
+        .iter()
+        .map(|device| {
+            let (sender, receiver) = latest::channel();
+            samples.push(receiver);
+            spawn_poller(
+                device.backend.clone(),
+                shutdown.clone(),
+                heartbeat_interval,
+                changes.clone(),
+                sender,
+                display_config.average_window,
+            )
+        })
+        .collect();
+
+    if let Some(port) = metrics_port() {
+        if let Err(e) = metrics::serve(port, backend.clone()) {
+            log::error!("can't serve metrics on port {}: {:?}", port, e);
+        }
+    }
+
+    if headless {
+        print_readings(&devices, &shutdown, headless_interval());
+        shut_down(&devices, &shutdown, pollers);
+        return Ok(());
+    }
+
+    let app = App::new().map_err(AppError::GUIError)?;
+    // `DARK_MODE=1` or `0` picks the starting theme, otherwise it's whatever was last chosen
+    let themes = [
+        ui_theme(&Theme::from_env()),
+        ui_theme(&Theme::dark_from_env()),
+    ];
+    let dark_mode = env_or("DARK_MODE", settings.dark_mode.unwrap_or(false) as u8) != 0;
+    // `SHOW_COUNTS` the same way for raw counts
+    app.set_show_counts(env_or("SHOW_COUNTS", settings.show_counts.unwrap_or(false) as u8) != 0);
+    app.set_dark_mode(dark_mode);
+    app.set_theme(themes[dark_mode as usize].clone());
+    app.set_show_raw(display_config.show_raw);
+    // a transform with a unit of its own, like the temperature sensor's °C, replaces the channel's
+    let transforms: Vec<DisplayTransform> = (0..backend::MAX_CHANNELS)
+        .map(|channel| {
+            read_source(&backend)
+                .backend()
+                .map_or(DisplayTransform::Linear, |be| be.transform(channel))
+        })
+        .collect();
+    let channel_displays: Vec<ChannelDisplay> = display_config
+        .channels
+        .iter()
+        .zip(transforms)
+        .map(|(channel, transform)| ChannelDisplay {
+            label: channel.label.as_str().into(),
+            unit: transform.unit().unwrap_or(&channel.unit).into(),
+            full_scale: channel.full_scale,
+        })
+        .collect();
+    app.set_channel_displays(ModelRc::new(VecModel::from(channel_displays)));
+
+    let device_views = Rc::new(VecModel::from(
+        devices
+            .iter()
+            .map(|device| DeviceView {
+                name: device.name.as_str().into(),
+                vref_mv: read_source(&device.backend)
+                    .backend()
+                    .map_or(VREF_MV, |be| be.vref_mv())
+                    .round() as i32,
+                ..DeviceView::default()
+            })
+            .collect::<Vec<_>>(),
     ));
+    app.set_devices(ModelRc::from(device_views.clone()));
 
-    backend
-        .write()
-        .map(|mut be| be.connect_socket().map_err(|e| AppError::BackendError(e)))
-        .unwrap()
-        .unwrap();
+    match display_config.load_artwork() {
+        GaugeArtwork::BuiltIn => {}
+        GaugeArtwork::Images { face, needle } => {
+            if let Some(face) = face {
+                app.set_face_image(face);
+            }
+            if let Some(needle) = needle {
+                app.set_needle_image(needle);
+            }
+        }
+        GaugeArtwork::Vector => app.set_vector_gauge(true),
+    }
+
+    let devices_clone = devices.clone();
+    app.on_click_reset_integral(move |device, channel| {
+        devices_clone[device as usize].with_backend(|be| be.reset_integral(channel as usize));
+    });
+
+    // changes made in the window are written back to the config file straight away
+    let settings = Arc::new(Mutex::new(settings));
+
+    let devices_clone = devices.clone();
+    let settings_clone = settings.clone();
+    app.on_toggle_channel(move |device, channel, enabled| {
+        let device = &devices_clone[device as usize];
+        device.with_backend(|be| be.set_channel_enabled(channel as usize, enabled));
+        match settings_clone.lock() {
+            Ok(mut settings) => {
+                let key = format!("a{}", channel);
+                settings
+                    .device_mut(&device.name)
+                    .enabled
+                    .insert(key, enabled);
+                settings.save_or_warn();
+            }
+            Err(e) => log::error!("error locking settings: {:?}", e),
+        }
+    });
+
+    let devices_clone = devices.clone();
+    let settings_clone = settings.clone();
+    app.on_change_vref(move |device, mv| {
+        let mv = mv.max(1) as f32;
+        let device = &devices_clone[device as usize];
+        device.with_backend(|be| be.set_vref_mv(mv));
+        match settings_clone.lock() {
+            Ok(mut settings) => {
+                settings.device_mut(&device.name).vref_mv = Some(mv);
+                settings.save_or_warn();
+            }
+            Err(e) => log::error!("error locking settings: {:?}", e),
+        }
+    });
+
+    // the slider starts where a0's filter is, moving it sets every channel of every device
+    app.set_filter_alpha(
+        read_source(&backend)
+            .backend()
+            .map_or(1.0, |be| be.filter_alpha(0)),
+    );
+    let devices_clone = devices.clone();
+    let settings_clone = settings.clone();
+    app.on_change_filter_alpha(move |alpha| {
+        for_each_backend(&devices_clone, |be| {
+            for channel in 0..backend::MAX_CHANNELS {
+                be.set_filter_alpha(channel, alpha);
+            }
+        });
+        match settings_clone.lock() {
+            Ok(mut settings) => {
+                settings.filter_alpha = Some(alpha);
+                settings.save_or_warn();
+            }
+            Err(e) => log::error!("error locking settings: {:?}", e),
+        }
+    });
+
+    app.set_poll_delay_ms(
+        read_source(&backend)
+            .backend()
+            .map_or(Duration::ZERO, |be| be.poll_delay())
+            .as_millis() as i32,
+    );
+    let devices_clone = devices.clone();
+    let settings_clone = settings.clone();
+    app.on_change_poll_delay(move |ms| {
+        let ms = ms.max(0) as u64;
+        for_each_backend(&devices_clone, |be| {
+            be.set_poll_delay(Duration::from_millis(ms))
+        });
+        match settings_clone.lock() {
+            Ok(mut settings) => {
+                settings.poll_delay_ms = Some(ms);
+                settings.save_or_warn();
+            }
+            Err(e) => log::error!("error locking settings: {:?}", e),
+        }
+    });
+
+    let app_weak = app.as_weak();
+    let settings_clone = settings.clone();
+    app.on_toggle_dark_mode(move |dark| {
+        if let Some(app) = app_weak.upgrade() {
+            app.set_theme(themes[dark as usize].clone());
+        }
+        match settings_clone.lock() {
+            Ok(mut settings) => {
+                settings.dark_mode = Some(dark);
+                settings.save_or_warn();
+            }
+            Err(e) => log::error!("error locking settings: {:?}", e),
+        }
+    });
+
+    let settings_clone = settings.clone();
+    app.on_toggle_counts(move |counts| match settings_clone.lock() {
+        Ok(mut settings) => {
+            settings.show_counts = Some(counts);
+            settings.save_or_warn();
+        }
+        Err(e) => log::error!("error locking settings: {:?}", e),
+    });
+
+    let devices_clone = devices.clone();
+    app.on_click_reset_peaks(move || for_each_backend(&devices_clone, |be| be.reset_extremes()));
+
+    let devices_clone = devices.clone();
+    app.on_click_reconnect(move || {
+        for_each_source(&devices_clone, |be| {
+            if let Err(e) = be.reconnect() {
+                log::error!("error reconnecting: {:?}", e);
+            }
+        })
+    });
 
-    // handle updates offthread
     let be_clone = backend.clone();
-    thread::spawn(move || {
-        let backend = be_clone;
-        log::info!("backend thread started");
-        loop {
-            match backend.write().map(|mut wl| match wl.poll() {
-                Ok(_) => {} // TODO: figure out if we're wasting cycles by not reading polled val here
-                Err(e) => {
-                    log::error!("error polling backend: {:?}", e);
-                }
-            }) {
-                Ok(_) => {}
-                Err(e) => {
-                    log::error!("error locking backend: {:?}", e);
-                }
+    let weak_app = app.as_weak();
+    let recording_path = std::env::var_os("RECORDING_PATH")
+        .map_or_else(|| PathBuf::from("readings.csv"), PathBuf::from);
+    app.on_toggle_recording(move |enabled| {
+        let mut source = write_source(&be_clone);
+        let Some(be) = source.backend_mut() else {
+            log::error!("this source can't record");
+            if let Some(app) = weak_app.upgrade() {
+                app.set_recording(false);
+            }
+            return;
+        };
+        if !enabled {
+            be.stop_recording();
+        } else if let Err(e) = be.start_recording(&recording_path) {
+            log::error!("error starting recording: {:?}", e);
+            if let Some(app) = weak_app.upgrade() {
+                app.set_recording(false);
             }
         }
     });
 
-    let app = App::new().map_err(|e| AppError::GUIError(e))?;
+    app.set_commands(ModelRc::new(VecModel::from(command_buttons())));
+    let devices_clone = devices.clone();
+    app.on_click_command(move |command| {
+        for_each_backend(&devices_clone, |be| {
+            if let Err(e) = be.send_command(&command) {
+                log::error!("error sending {:?}: {:?}", command, e);
+            }
+        })
+    });
+
+    // `SNAPSHOT_DIR` is where the snapshot button writes, the working directory by default
+    let be_clone = backend.clone();
+    let snapshot_dir =
+        std::env::var_os("SNAPSHOT_DIR").map_or_else(|| PathBuf::from("."), PathBuf::from);
+    app.on_click_snapshot(move || {
+        // the file is written after the lock is released
+        let snapshot = Snapshot::of(read_source(&be_clone).as_ref());
+        match snapshot.save_in(&snapshot_dir) {
+            Ok(path) => log::info!("snapshot written to {}", path.display()),
+            Err(e) => log::error!("error writing snapshot: {:?}", e),
+        }
+    });
 
+    // runs on the UI thread, so it draws at most once per tick however fast the devices are polled
+    // it only takes the samples the poll threads publish, never a backend's lock
     let weak_app = app.as_weak();
-    thread::spawn(move || {
-        let app = weak_app;
-
-        loop {
-            // thread::sleep(std::time::Duration::from_millis(1));
-
-            let (a0, a1, a2, a3) = match backend.read().map(|be| {
-                be.read().map(|vals| {
-                    // TODO: Why read here instead of poll?
-                    (
-                        vals.a0 as i32,
-                        vals.a1 as i32,
-                        vals.a2 as i32,
-                        vals.a3 as i32,
-                    )
-                })
-            }) {
-                Ok(v) => match v {
-                    Ok(v) => v,
-                    Err(e) => {
-                        log::error!("error reading backend: {:?}", e);
-                        continue;
-                    }
-                },
-                Err(e) => {
-                    log::error!("error locking backend: {:?}", e);
-                    continue;
+    let stop = shutdown.clone();
+    let mut smoothers: Vec<DisplaySmoother> = devices
+        .iter()
+        .map(|_| DisplaySmoother::new(display_config.smoothing))
+        .collect();
+    let mut shown_revisions = vec![None; devices.len()];
+    // the newest readings of each device, kept for the smoothing to keep easing towards
+    let mut states: Vec<Option<FrontendState>> = devices.iter().map(|_| None).collect();
+    // raw values last drawn, for the minimum deltas
+    let mut shown_values: Vec<Option<Vec<u16>>> = vec![None; devices.len()];
+    let mut read_errors: Vec<ErrorThrottle> = devices
+        .iter()
+        .map(|_| ErrorThrottle::new("error reading backend"))
+        .collect();
+    let mut shown_history = Vec::new();
+    let mut last_stats = Instant::now();
+    let mut was_frozen = false;
+    // integrals grow every poll even when the values themselves don't change
+    let integrating = display_config.integrated.contains(&true);
+    let updater = slint::Timer::default();
+    updater.start(slint::TimerMode::Repeated, UI_REFRESH, move || {
+        let Some(handle) = weak_app.upgrade() else {
+            return;
+        };
+
+        // a signal stops the poll threads before the window closes, close it too
+        if stop.load(Ordering::Relaxed) {
+            let _ = slint::quit_event_loop();
+            return;
+        }
+
+        // the status lines keep updating, only the readings hold still
+        let frozen = handle.get_frozen();
+        if frozen != was_frozen {
+            log::info!("display {}", if frozen { "frozen" } else { "unfrozen" });
+            was_frozen = frozen;
+            if !frozen {
+                smoothers.iter_mut().for_each(DisplaySmoother::reset);
+                shown_revisions.fill(None);
+                shown_values.fill(None);
+            }
+        }
+
+        for (index, samples) in samples.iter().enumerate() {
+            // nothing new since the last tick, but the smoothing may still be under way
+            let sample = samples.take();
+            let Some(mut view) = device_views.row_data(index) else {
+                continue;
+            };
+            let mut changed = false;
+
+            if let Some(sample) = sample {
+                // pushed on its own, it matters most when there are no values to show
+                let connection = sample.connection;
+                let status = connection_status(connection);
+                let text: slint::SharedString = connection.to_string().into();
+                if view.connection_status != status || view.connection_text != text {
+                    view.connection_status = status;
+                    view.connection_text = text.clone();
+                    changed = true;
                 }
+
+                if index == 0 {
+                    let history = sample.connection_history;
+                    if history != shown_history {
+                        let lines: Vec<slint::SharedString> = history
+                            .iter()
+                            .map(|event| event.to_string().into())
+                            .collect();
+                        shown_history = history;
+                        handle.set_connection_history(ModelRc::new(VecModel::from(lines)));
+                    }
+
+                    handle.set_connection_status(status);
+                    handle.set_connection_text(text);
+
+                    // pushed on its own too, polls keep failing while there are no values to show
+                    if last_stats.elapsed() >= STATS_REFRESH {
+                        last_stats = Instant::now();
+                        let stats = sample.stats;
+                        handle.set_poll_rate(stats.rate() as f32);
+                        handle.set_dropped_polls(stats.dropped() as i32);
+                        handle.set_interval_ms(stats.interval_mean.as_secs_f32() * 1000.0);
+                        handle.set_jitter_ms(stats.interval_std_dev.as_secs_f32() * 1000.0);
+                        handle.set_hitches(stats.hitches as i32);
+                    }
+                }
+
+                // kept while frozen too, so unfreezing shows the newest readings rather than the stale ones
+                match sample.state {
+                    Ok(state) => {
+                        read_errors[index].clear();
+                        states[index] = Some(state);
+                    }
+                    Err(e) => read_errors[index].error(&e),
+                }
+            }
+            let state = if frozen { None } else { states[index].as_ref() };
+
+            // the backend keeps polling and recording at full rate, only drawing waits for a big enough move
+            // unchanged values with a new revision are a reset or the like, and always drawn
+            let fresh = |state: &FrontendState| {
+                shown_revisions[index] != Some(state.revision)
+                    && shown_values[index].as_ref().is_none_or(|pushed| {
+                        *pushed == state.values
+                            || display_config.moved_enough(pushed, &state.values)
+                    })
             };
+            if let Some(state) =
+                state.filter(|state| fresh(state) || !smoothers[index].settled() || integrating)
+            {
+                shown_revisions[index] = Some(state.revision);
+                shown_values[index] = Some(state.values.clone());
+                let channels = channel_views(
+                    state,
+                    &display_config,
+                    &mut smoothers[index],
+                    history_window,
+                );
+                let raw_values: Vec<i32> = state.raw.iter().map(|&raw| raw as i32).collect();
+                view.channels = ModelRc::new(VecModel::from(channels));
+                view.raw_values = ModelRc::new(VecModel::from(raw_values));
+                changed = true;
 
-            match app.upgrade_in_event_loop(move |handle| {
-                handle.set_a0(a0);
-                handle.set_a1(a1);
-                handle.set_a2(a2);
-                handle.set_a3(a3);
-            }) {
-                Ok(_) => {}
-                Err(e) => {
-                    log::error!("error updating frontend: {:?}", e);
+                if index == 0 {
+                    handle.set_device_info(
+                        state
+                            .device_info
+                            .as_ref()
+                            .map(|info| info.to_string())
+                            .unwrap_or_default()
+                            .into(),
+                    );
+                    handle.set_show_status(state.status_byte);
+                    handle.set_recording(state.recording);
+                    handle.set_device_status(DeviceStatus {
+                        over_temp: state.flags.over_temp(),
+                        low_battery: state.flags.low_battery(),
+                        sensor_fault: state.flags.sensor_fault(),
+                    });
                 }
             }
+
+            if changed {
+                device_views.set_row_data(index, view);
+            }
         }
     });
 
-    Ok(app.run().map_err(|e| AppError::GUIError(e))?)
+    let result = app.run().map_err(AppError::GUIError);
+
+    updater.stop();
+    shut_down(&devices, &shutdown, pollers);
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn poller_keeps_polling_while_the_frontend_is_slow() {
+        let backend: SharedSource = Arc::new(RwLock::new(Box::new(MockBackend::new())));
+        let stop = Arc::new(AtomicBool::new(false));
+        let (sender, samples) = latest::channel();
+        let poller = spawn_poller(
+            backend.clone(),
+            stop.clone(),
+            None,
+            Arc::new(AtomicU64::new(0)),
+            sender,
+            None,
+        );
+
+        // a frontend that spends 200 ms on every sample it takes
+        let mut revisions = Vec::new();
+        while revisions.len() < 3 {
+            let Some(sample) = samples.take() else {
+                thread::sleep(UI_REFRESH);
+                continue;
+            };
+            revisions.push(sample.state.unwrap().revision);
+            thread::sleep(Duration::from_millis(200));
+        }
+        stop.store(true, Ordering::Relaxed);
+        poller.join().unwrap();
+
+        // the mock polls every 10 ms, so each hold lets about 20 polls through
+        assert!(
+            revisions.windows(2).all(|pair| pair[1] > pair[0] + 5),
+            "{:?}",
+            revisions
+        );
+        // what's left is from the last poll or two, the poller publishes at most once per UI_REFRESH
+        let polled = read_source(&backend).value_revision();
+        let last = samples.take().unwrap().state.unwrap().revision;
+        assert!(
+            last > revisions[2] && last + 2 >= polled,
+            "took {} of {}",
+            last,
+            polled
+        );
+    }
 }
@@ -0,0 +1,158 @@
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener, TcpStream};
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::Duration;
+
+use crate::backend::ConnectionState;
+use crate::source::{read_source, AnalogSource};
+
+/// how long a scraper gets to send its request before it's dropped
+static REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// the latest readings and poll counters in the Prometheus text exposition format
+pub fn render(be: &dyn AnalogSource) -> String {
+    let mut out = String::new();
+
+    if let (Ok(vals), Ok(shown)) = (be.read_raw(), be.read_display()) {
+        out.push_str("# HELP rp2040_adc_raw Latest ADC counts of each channel.\n");
+        out.push_str("# TYPE rp2040_adc_raw gauge\n");
+        for (channel, raw) in vals.iter().enumerate() {
+            let _ = writeln!(out, "rp2040_adc_raw{{channel=\"a{}\"}} {}", channel, raw);
+        }
+        out.push_str(
+            "# HELP rp2040_adc_value Latest reading of each channel in its display unit.\n",
+        );
+        out.push_str("# TYPE rp2040_adc_value gauge\n");
+        for (channel, value) in shown.iter().enumerate() {
+            let _ = writeln!(
+                out,
+                "rp2040_adc_value{{channel=\"a{}\"}} {}",
+                channel, value
+            );
+        }
+    }
+
+    let stats = be.stats();
+    let counters = [
+        ("polls", "Successful polls.", stats.polls),
+        (
+            "poll_timeouts",
+            "Polls the device didn't answer in time.",
+            stats.timeouts,
+        ),
+        (
+            "poll_failures",
+            "Polls that failed any other way.",
+            stats.failures,
+        ),
+        (
+            "poll_out_of_order",
+            "Frames dropped for arriving late.",
+            stats.out_of_order,
+        ),
+    ];
+    for (name, help, count) in counters {
+        let _ = writeln!(out, "# HELP rp2040_{}_total {}", name, help);
+        let _ = writeln!(out, "# TYPE rp2040_{}_total counter", name);
+        let _ = writeln!(out, "rp2040_{}_total {}", name, count);
+    }
+
+    out.push_str("# HELP rp2040_connected Whether the device is answering polls.\n");
+    out.push_str("# TYPE rp2040_connected gauge\n");
+    let connected = be.connection_state() == ConnectionState::Connected;
+    let _ = writeln!(out, "rp2040_connected {}", connected as u8);
+
+    out
+}
+
+/// serve render() at `/metrics` on `port` from a thread of its own, returning the address it's on
+/// each connection gets a thread too, so a slow scraper holds up neither the others nor the poll thread
+pub fn serve(port: u16, backend: Arc<RwLock<Box<dyn AnalogSource>>>) -> io::Result<SocketAddr> {
+    let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::UNSPECIFIED, port)))?;
+    let addr = listener.local_addr()?;
+    log::info!("serving metrics on {}", addr);
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let backend = backend.clone();
+            let result = stream.and_then(|stream| {
+                thread::Builder::new()
+                    .name(String::from("metrics"))
+                    .spawn(move || {
+                        if let Err(e) = respond(stream, &backend) {
+                            log::warn!("error serving metrics: {:?}", e);
+                        }
+                    })
+                    .map(|_| ())
+            });
+            if let Err(e) = result {
+                log::warn!("error serving metrics: {:?}", e);
+            }
+        }
+    });
+    Ok(addr)
+}
+
+/// answer one request, anything but `GET /metrics` is a 404
+fn respond(mut stream: TcpStream, backend: &RwLock<Box<dyn AnalogSource>>) -> io::Result<()> {
+    stream.set_read_timeout(Some(REQUEST_TIMEOUT))?;
+    stream.set_write_timeout(Some(REQUEST_TIMEOUT))?;
+    let mut request_line = String::new();
+    BufReader::new(&stream).read_line(&mut request_line)?;
+
+    let mut parts = request_line.split_whitespace();
+    let (status, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => ("200 OK", render(read_source(backend).as_ref())),
+        _ => ("404 Not Found", String::from("only /metrics is served\n")),
+    };
+
+    write!(
+        stream,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        body.len(),
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::MockBackend;
+
+    #[test]
+    fn render_lists_every_channel_and_counter() {
+        let mut be = MockBackend::new();
+        let before = render(&be);
+        assert!(!before.contains("rp2040_adc_raw"));
+        assert!(before.contains("rp2040_polls_total 0\n"));
+
+        be.poll().unwrap();
+        let raw = be.read().unwrap()[0];
+        let metrics = render(&be);
+        assert!(metrics.contains(&format!("rp2040_adc_raw{{channel=\"a0\"}} {}\n", raw)));
+        assert!(metrics.contains("rp2040_adc_value{channel=\"a3\"}"));
+        assert!(metrics.contains("rp2040_polls_total 1\n"));
+        assert!(metrics.contains("rp2040_connected 1\n"));
+    }
+
+    #[test]
+    fn stalled_scraper_doesnt_hold_up_the_next() {
+        let backend: Arc<RwLock<Box<dyn AnalogSource>>> =
+            Arc::new(RwLock::new(Box::new(MockBackend::new())));
+        let port = serve(0, backend).unwrap().port();
+        let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, port));
+
+        // connects and never sends its request
+        let _stalled = TcpStream::connect(addr).unwrap();
+
+        let mut scraper = TcpStream::connect(addr).unwrap();
+        scraper.set_read_timeout(Some(REQUEST_TIMEOUT / 5)).unwrap();
+        scraper.write_all(b"GET /metrics HTTP/1.1\r\n\r\n").unwrap();
+        let mut status = String::new();
+        BufReader::new(&scraper).read_line(&mut status).unwrap();
+        assert_eq!(status, "HTTP/1.1 200 OK\r\n");
+    }
+}
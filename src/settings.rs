@@ -0,0 +1,291 @@
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::backend::{Calibration, DisplayTransform, Thresholds};
+use crate::display::DisplayConfig;
+
+/// settings kept between runs, anything unset keeps the built-in default
+/// environment variables still win over the file
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub local_addr: Option<SocketAddr>,
+    pub remote_addr: Option<SocketAddr>,
+    pub poll_delay_ms: Option<u64>,
+    /// EMA weight of each new sample, see the filter slider
+    pub filter_alpha: Option<f32>,
+    /// which of the two themes the window starts with
+    pub dark_mode: Option<bool>,
+    /// readings in raw ADC counts rather than each channel's unit, unfiltered
+    pub show_counts: Option<bool>,
+    /// keyed by channel name, `a0`, `a1`, ...
+    pub channels: BTreeMap<String, ChannelSettings>,
+    /// keyed by the device's address in `REMOTE_ADDRS`, a lone device is `default`
+    pub devices: BTreeMap<String, DeviceSettings>,
+    /// set when the file was there but couldn't be loaded, so saving doesn't replace it with defaults
+    #[serde(skip)]
+    pub unreadable: bool,
+}
+
+/// everything that can be set for one channel
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChannelSettings {
+    pub label: Option<String>,
+    pub unit: Option<String>,
+    pub full_scale: Option<f32>,
+    pub calibration: Option<Calibration>,
+    /// `"linear"`, `"temperature"`, `{ custom = [scale, offset] }` or `{ logarithmic = { reference = mv } }`
+    pub transform: Option<DisplayTransform>,
+    pub thresholds: Option<Thresholds>,
+}
+
+/// everything that can be set for one device
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceSettings {
+    /// measured ADC reference in mV
+    pub vref_mv: Option<f32>,
+    /// keyed by channel name, false hides the gauge's reading and skips its alarms and peaks
+    /// a channel that isn't listed is enabled
+    pub enabled: BTreeMap<String, bool>,
+}
+
+impl Settings {
+    /// `CONFIG_PATH`, or `rp2040-udp-viewer/config.toml` in the platform's config directory
+    pub fn path() -> Option<PathBuf> {
+        match std::env::var_os("CONFIG_PATH") {
+            Some(path) => Some(PathBuf::from(path)),
+            None => config_dir().map(|dir| dir.join("rp2040-udp-viewer").join("config.toml")),
+        }
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("could not read {}: {:?}", path.display(), e))?;
+        toml::from_str(&contents).map_err(|e| format!("invalid config {}: {}", path.display(), e))
+    }
+
+    /// load the settings from path(), with a warning and the defaults if that doesn't work
+    /// a missing file is normal on first run and only logged
+    pub fn load_or_default() -> Self {
+        match Self::path() {
+            Some(path) => Self::load_or_default_at(&path),
+            None => {
+                log::warn!("no config directory found, settings won't be kept");
+                Settings::default()
+            }
+        }
+    }
+
+    /// load_or_default() from `path`
+    /// a file that's there but can't be loaded is left alone, nothing is saved over it
+    pub fn load_or_default_at(path: &Path) -> Self {
+        if !path.exists() {
+            log::info!("no config at {}, using defaults", path.display());
+            return Settings::default();
+        }
+
+        Self::load(path).unwrap_or_else(|e| {
+            log::warn!(
+                "{}, using defaults and not saving changes until it's fixed",
+                e
+            );
+            Settings {
+                unreadable: true,
+                ..Settings::default()
+            }
+        })
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if self.unreadable {
+            return Err(format!(
+                "not saving settings over {}, it couldn't be loaded",
+                path.display()
+            ));
+        }
+        let contents =
+            toml::to_string_pretty(self).map_err(|e| format!("could not encode config: {}", e))?;
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .map_err(|e| format!("could not create {}: {:?}", dir.display(), e))?;
+        }
+        std::fs::write(path, contents)
+            .map_err(|e| format!("could not write {}: {:?}", path.display(), e))
+    }
+
+    /// write the settings to path(), logging rather than failing
+    pub fn save_or_warn(&self) {
+        match Self::path() {
+            Some(path) => {
+                if let Err(e) = self.save(&path) {
+                    log::warn!("{}", e);
+                }
+            }
+            None => log::warn!("no config directory found, settings won't be kept"),
+        }
+    }
+
+    pub fn channel(&self, channel: usize) -> Option<&ChannelSettings> {
+        self.channels.get(&format!("a{}", channel))
+    }
+
+    /// `name` is the device's, empty for a lone one
+    pub fn device(&self, name: &str) -> Option<&DeviceSettings> {
+        self.devices.get(device_key(name))
+    }
+
+    pub fn device_mut(&mut self, name: &str) -> &mut DeviceSettings {
+        self.devices
+            .entry(device_key(name).to_string())
+            .or_default()
+    }
+
+    /// whether `channel` of the device called `name` is enabled
+    pub fn enabled(&self, name: &str, channel: usize) -> bool {
+        self.device(name)
+            .and_then(|device| device.enabled.get(&format!("a{}", channel)).copied())
+            .unwrap_or(true)
+    }
+
+    pub fn calibration(&self, channel: usize) -> Calibration {
+        self.channel(channel)
+            .and_then(|settings| settings.calibration)
+            .unwrap_or_default()
+    }
+
+    pub fn transform(&self, channel: usize) -> DisplayTransform {
+        self.channel(channel)
+            .and_then(|settings| settings.transform)
+            .unwrap_or_default()
+    }
+
+    pub fn thresholds(&self, channel: usize) -> Thresholds {
+        self.channel(channel)
+            .and_then(|settings| settings.thresholds)
+            .unwrap_or_default()
+    }
+
+    /// label, unit and full scale of each channel, where the environment doesn't set them
+    pub fn apply_display(&self, config: &mut DisplayConfig) {
+        for (i, display) in config.channels.iter_mut().enumerate() {
+            let Some(settings) = self.channel(i) else {
+                continue;
+            };
+            let unset = |var: &str| std::env::var_os(format!("{}_A{}", var, i)).is_none();
+
+            if let Some(label) = settings.label.as_ref().filter(|_| unset("LABEL")) {
+                display.label = label.clone();
+            }
+            if let Some(unit) = settings.unit.as_ref().filter(|_| unset("UNIT")) {
+                display.unit = unit.clone();
+            }
+            if let Some(full_scale) = settings.full_scale.filter(|_| unset("FULL_SCALE")) {
+                display.full_scale = full_scale;
+            }
+        }
+    }
+}
+
+/// where a device's settings are kept, a lone device has no name of its own
+fn device_key(name: &str) -> &str {
+    if name.is_empty() {
+        "default"
+    } else {
+        name
+    }
+}
+
+/// where per-user configuration lives on this platform
+fn config_dir() -> Option<PathBuf> {
+    let home = || std::env::var_os("HOME").map(PathBuf::from);
+
+    if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else if cfg!(target_os = "macos") {
+        home().map(|home| home.join("Library").join("Application Support"))
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| home().map(|home| home.join(".config")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn settings_survive_a_round_trip() {
+        let path = std::env::temp_dir().join(format!("settings-{}.toml", std::process::id()));
+        let mut settings = Settings {
+            remote_addr: Some("10.0.0.7:4000".parse().unwrap()),
+            poll_delay_ms: Some(50),
+            ..Settings::default()
+        };
+        settings.channels.insert(
+            String::from("a1"),
+            ChannelSettings {
+                unit: Some(String::from("V")),
+                calibration: Some("0.001:0".parse().unwrap()),
+                transform: Some(DisplayTransform::Custom(2.0, 1.0)),
+                thresholds: Some(":3.0".parse().unwrap()),
+                ..ChannelSettings::default()
+            },
+        );
+        settings.device_mut("10.0.0.7:4000").vref_mv = Some(3280.0);
+        settings.device_mut("").vref_mv = Some(3310.0);
+        settings
+            .device_mut("10.0.0.7:4000")
+            .enabled
+            .insert(String::from("a2"), false);
+
+        settings.save(&path).unwrap();
+        let loaded = Settings::load(&path).unwrap();
+        let _ = std::fs::remove_file(&path);
+
+        assert_eq!(loaded, settings);
+        assert_eq!(loaded.calibration(1).scale, 0.001);
+        assert_eq!(loaded.calibration(0), Calibration::default());
+        assert_eq!(
+            loaded.device("10.0.0.7:4000").unwrap().vref_mv,
+            Some(3280.0)
+        );
+        assert_eq!(loaded.device("").unwrap().vref_mv, Some(3310.0));
+        assert!(loaded.device("10.0.0.8:4000").is_none());
+        assert!(!loaded.enabled("10.0.0.7:4000", 2));
+        assert!(loaded.enabled("10.0.0.7:4000", 1));
+        assert!(loaded.enabled("", 2));
+    }
+
+    #[test]
+    fn malformed_settings_are_an_error() {
+        let path = std::env::temp_dir().join(format!("malformed-{}.toml", std::process::id()));
+        std::fs::write(&path, "poll_delay_ms = \"soon\"").unwrap();
+        let loaded = Settings::load(&path);
+        let _ = std::fs::remove_file(&path);
+
+        assert!(loaded.is_err());
+    }
+
+    #[test]
+    fn malformed_settings_survive_a_save() {
+        let path = std::env::temp_dir().join(format!("kept-{}.toml", std::process::id()));
+        let contents = "remote_addr = \"10.0.0.7:4000\"\npoll_delay_ms = \"soon\"\n";
+        std::fs::write(&path, contents).unwrap();
+
+        let mut settings = Settings::load_or_default_at(&path);
+        assert!(settings.unreadable);
+        settings.dark_mode = Some(true);
+        let saved = settings.save(&path);
+        let kept = std::fs::read_to_string(&path);
+        let _ = std::fs::remove_file(&path);
+
+        assert!(saved.is_err());
+        assert_eq!(kept.unwrap(), contents);
+    }
+}
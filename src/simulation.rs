@@ -0,0 +1,273 @@
+use std::io;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::backend::{AnalogValues, PollStats, ViewerBackendError, DEFAULT_CHANNELS};
+use crate::source::AnalogSource;
+use crate::transport::Transport;
+
+/// highest value the simulated 12-bit ADC can read
+static ADC_MAX: f32 = 4095.0;
+/// the mock has no device to wait for, so it paces the poll loop itself
+static MOCK_POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// time the last mock channel takes to sweep from 0 to full scale
+static MOCK_SWEEP_PERIOD: Duration = Duration::from_secs(10);
+
+/// shape of the noise added to the simulated channels
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum NoiseKind {
+    /// evenly spread within ±amplitude
+    #[default]
+    Uniform,
+    /// normally distributed, amplitude is the standard deviation
+    Gaussian,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SimulationConfig {
+    pub noise: NoiseKind,
+    /// in raw counts, 0 for perfectly clean signals
+    pub noise_amplitude: f32,
+}
+
+impl SimulationConfig {
+    /// read simulation settings from the environment, falling back to defaults
+    /// `SIM_NOISE` is `uniform` or `gaussian`, `SIM_NOISE_COUNTS` its amplitude
+    pub fn from_env() -> Self {
+        let mut config = SimulationConfig::default();
+
+        if let Ok(kind) = std::env::var("SIM_NOISE") {
+            match kind.trim().to_ascii_lowercase().as_str() {
+                "uniform" => config.noise = NoiseKind::Uniform,
+                "gaussian" => config.noise = NoiseKind::Gaussian,
+                other => log::warn!("ignoring SIM_NOISE: unknown noise {:?}", other),
+            }
+        }
+
+        if let Ok(counts) = std::env::var("SIM_NOISE_COUNTS") {
+            match counts.trim().parse() {
+                Ok(counts) => config.noise_amplitude = counts,
+                Err(e) => log::warn!("ignoring SIM_NOISE_COUNTS: {:?}", e),
+            }
+        }
+
+        config
+    }
+}
+
+/// stands in for the RP2040 so the viewer can be demoed without hardware
+/// each channel is a slow sine wave at its own period, plus the configured noise
+pub struct SimulatedDevice {
+    config: SimulationConfig,
+    started: Instant,
+    rng: u64,
+    last_request: Vec<u8>,
+}
+
+impl SimulatedDevice {
+    pub fn new(config: SimulationConfig) -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |t| t.as_nanos() as u64);
+        Self::with_seed(config, seed)
+    }
+
+    /// the same seed makes the same noise every time
+    pub fn with_seed(config: SimulationConfig, seed: u64) -> Self {
+        SimulatedDevice {
+            config,
+            started: Instant::now(),
+            rng: seed | 1, // xorshift never leaves 0
+            last_request: Vec::new(),
+        }
+    }
+
+    /// next pseudo-random number in [0, 1)
+    fn next_random(&mut self) -> f32 {
+        // xorshift64, plenty for jittering needles
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        (self.rng >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn noise(&mut self) -> f32 {
+        let amplitude = self.config.noise_amplitude;
+        if amplitude == 0.0 {
+            return 0.0;
+        }
+
+        match self.config.noise {
+            NoiseKind::Uniform => (self.next_random() * 2.0 - 1.0) * amplitude,
+            NoiseKind::Gaussian => {
+                // Box-Muller
+                let u1 = self.next_random().max(f32::MIN_POSITIVE);
+                let u2 = self.next_random();
+                (-2.0 * u1.ln()).sqrt() * (std::f32::consts::TAU * u2).cos() * amplitude
+            }
+        }
+    }
+
+    fn sample(&mut self, channel: usize) -> u16 {
+        let t = self.started.elapsed().as_secs_f32();
+        let period = 2.0 + channel as f32;
+        let clean = ADC_MAX / 2.0 * (1.0 + 0.9 * (std::f32::consts::TAU * t / period).sin());
+
+        (clean + self.noise()).round().clamp(0.0, ADC_MAX) as u16
+    }
+}
+
+impl Transport for SimulatedDevice {
+    fn send(&mut self, msg: &[u8]) -> io::Result<()> {
+        self.last_request = msg.to_vec();
+        Ok(())
+    }
+
+    /// answers `init` with a bare ack and everything else with a frame of samples
+    /// as many channels as fit in `frame_len`, without status, sequence or checksum bytes
+    /// main.rs leaves those off the frame format when simulating
+    fn recv(&mut self, buf: &mut [u8], frame_len: usize) -> io::Result<usize> {
+        if self.last_request == b"init" {
+            buf[..3].copy_from_slice(b"ack");
+            return Ok(3);
+        }
+
+        let channels = frame_len / 2;
+        for channel in 0..channels {
+            let sample = self.sample(channel).to_be_bytes();
+            buf[channel * 2..channel * 2 + 2].copy_from_slice(&sample);
+        }
+        // a status byte with no flags set, if the frame has room for one
+        buf[channels * 2..frame_len].fill(0);
+
+        Ok(frame_len)
+    }
+
+    fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        Ok(None)
+    }
+
+    fn set_read_timeout(&mut self, _timeout: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// skips the backend and its protocol altogether and makes up smooth readings
+/// the first channels are sine waves, the last sweeps the whole range, for working on the GUI
+pub struct MockBackend {
+    started: Instant,
+    values: AnalogValues,
+    revision: u64,
+    polled: u32,
+}
+
+impl MockBackend {
+    pub fn new() -> Self {
+        MockBackend {
+            started: Instant::now(),
+            values: AnalogValues {
+                channels: Vec::new(),
+            },
+            revision: 0,
+            polled: 0,
+        }
+    }
+}
+
+impl AnalogSource for MockBackend {
+    fn connect_socket(&mut self) -> Result<(), ViewerBackendError> {
+        Ok(())
+    }
+
+    fn poll(&mut self) -> Result<&AnalogValues, ViewerBackendError> {
+        thread::sleep(MOCK_POLL_INTERVAL);
+
+        let t = self.started.elapsed().as_secs_f32();
+        let sweep = t / MOCK_SWEEP_PERIOD.as_secs_f32() % 1.0;
+        self.values.channels = (0..DEFAULT_CHANNELS)
+            .map(|channel| {
+                let value = if channel == DEFAULT_CHANNELS - 1 {
+                    sweep * ADC_MAX
+                } else {
+                    let period = 2.0 + channel as f32;
+                    ADC_MAX / 2.0 * (1.0 + 0.9 * (std::f32::consts::TAU * t / period).sin())
+                };
+                value.round() as u16
+            })
+            .collect();
+
+        self.revision += 1;
+        self.polled += 1;
+        Ok(&self.values)
+    }
+
+    fn read(&self) -> Result<&AnalogValues, ViewerBackendError> {
+        if self.values.is_empty() {
+            Err(ViewerBackendError::ParserError(String::from(
+                "no values read yet",
+            )))?
+        }
+        Ok(&self.values)
+    }
+
+    fn value_revision(&self) -> u64 {
+        self.revision
+    }
+
+    fn stats(&self) -> PollStats {
+        PollStats {
+            polls: self.polled,
+            elapsed: self.started.elapsed(),
+            ..PollStats::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn noisy(noise: NoiseKind, noise_amplitude: f32, seed: u64) -> SimulatedDevice {
+        SimulatedDevice::with_seed(
+            SimulationConfig {
+                noise,
+                noise_amplitude,
+            },
+            seed,
+        )
+    }
+
+    #[test]
+    fn noisy_samples_stay_in_the_adc_range() {
+        let mut device = noisy(NoiseKind::Gaussian, 10_000.0, 7);
+        device.send(b"poll").unwrap();
+
+        let mut values = Vec::new();
+        let mut buf = [0; 8];
+        for _ in 0..200 {
+            assert_eq!(device.recv(&mut buf, 8).unwrap(), 8);
+            values.extend(
+                buf.chunks(2)
+                    .map(|pair| u16::from_be_bytes([pair[0], pair[1]])),
+            );
+        }
+        assert!(values.iter().all(|&value| value <= ADC_MAX as u16));
+        // noise that large is clamped at both ends
+        assert!(values.contains(&0));
+        assert!(values.contains(&(ADC_MAX as u16)));
+    }
+
+    #[test]
+    fn fixed_seed_repeats_the_noise() {
+        let sequence = |seed| {
+            let mut device = noisy(NoiseKind::Uniform, 100.0, seed);
+            (0..100).map(|_| device.noise()).collect::<Vec<f32>>()
+        };
+
+        let first = sequence(41);
+        assert_eq!(first, sequence(41));
+        assert_ne!(first, sequence(43));
+        assert!(first.iter().all(|noise| noise.abs() <= 100.0));
+        assert!(first.iter().any(|&noise| noise != first[0]));
+    }
+}
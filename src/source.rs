@@ -0,0 +1,193 @@
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+use std::time::Duration;
+
+use crate::backend::{
+    AnalogValues, Calibration, ConnectionEvent, ConnectionState, PollStats, ViewerBackend,
+    ViewerBackendError,
+};
+
+/// lock `source` to read it, even if a thread panicked while holding it
+/// the poison is logged and cleared, what the panic interrupted is left for the next poll to sort out
+pub fn read_source(
+    source: &RwLock<Box<dyn AnalogSource>>,
+) -> RwLockReadGuard<'_, Box<dyn AnalogSource>> {
+    source.read().unwrap_or_else(|e| {
+        log::error!("a thread panicked holding the backend lock, carrying on");
+        source.clear_poison();
+        e.into_inner()
+    })
+}
+
+/// lock `source` to change it, recovering it from a panic like read_source()
+pub fn write_source(
+    source: &RwLock<Box<dyn AnalogSource>>,
+) -> RwLockWriteGuard<'_, Box<dyn AnalogSource>> {
+    source.write().unwrap_or_else(|e| {
+        log::error!("a thread panicked holding the backend lock, carrying on");
+        source.clear_poison();
+        e.into_inner()
+    })
+}
+
+/// where the frontend gets its readings from
+/// only what the poll loop and the gauges need, anything only a device has is on backend()
+pub trait AnalogSource: Send + Sync {
+    fn connect_socket(&mut self) -> Result<(), ViewerBackendError>;
+
+    /// fetch fresh values, called in a loop by the poll thread
+    fn poll(&mut self) -> Result<&AnalogValues, ViewerBackendError>;
+
+    /// poll() without waiting for the reply, `Ok(None)` while there's nothing new yet
+    fn try_poll(&mut self) -> Result<Option<&AnalogValues>, ViewerBackendError> {
+        self.poll().map(Some)
+    }
+
+    /// how long until poll() would talk to the device again, zero if it would now
+    fn next_poll_in(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    /// the last polled values, errors until the first poll succeeded
+    fn read(&self) -> Result<&AnalogValues, ViewerBackendError>;
+
+    /// bumped whenever what read() returns changes
+    fn value_revision(&self) -> u64;
+
+    fn stats(&self) -> PollStats {
+        PollStats::default()
+    }
+
+    fn reconnect(&mut self) -> Result<(), ViewerBackendError> {
+        self.connect_socket()
+    }
+
+    /// called once when the viewer exits
+    fn shutdown(&mut self) -> Result<(), ViewerBackendError> {
+        Ok(())
+    }
+
+    /// sources that aren't a device are always connected
+    fn connection_state(&self) -> ConnectionState {
+        ConnectionState::Connected
+    }
+
+    fn connection_history(&self) -> Vec<ConnectionEvent> {
+        Vec::new()
+    }
+
+    /// the device behind this source, None for one that makes its readings up
+    fn backend(&self) -> Option<&ViewerBackend> {
+        None
+    }
+
+    fn backend_mut(&mut self) -> Option<&mut ViewerBackend> {
+        None
+    }
+
+    /// the last values as the device sent them, before any filtering
+    fn read_raw(&self) -> Result<&AnalogValues, ViewerBackendError> {
+        match self.backend() {
+            Some(be) => be.read_raw(),
+            None => self.read(),
+        }
+    }
+
+    /// the last read values in mV, through the device's calibrations if there is one
+    fn read_calibrated(&self) -> Result<Vec<f32>, ViewerBackendError> {
+        match self.backend() {
+            Some(be) => be.read_calibrated(),
+            None => Ok(self
+                .read()?
+                .iter()
+                .map(|&raw| Calibration::default().to_mv(raw as f32))
+                .collect()),
+        }
+    }
+
+    /// the last read values as the gauges show them, see ViewerBackend::read_display()
+    fn read_display(&self) -> Result<Vec<f32>, ViewerBackendError> {
+        match self.backend() {
+            Some(be) => be.read_display(),
+            None => self.read_calibrated(),
+        }
+    }
+}
+
+impl AnalogSource for ViewerBackend {
+    fn connect_socket(&mut self) -> Result<(), ViewerBackendError> {
+        ViewerBackend::connect_socket(self)
+    }
+
+    fn poll(&mut self) -> Result<&AnalogValues, ViewerBackendError> {
+        ViewerBackend::poll(self)
+    }
+
+    fn try_poll(&mut self) -> Result<Option<&AnalogValues>, ViewerBackendError> {
+        ViewerBackend::try_poll(self)
+    }
+
+    fn next_poll_in(&self) -> Duration {
+        ViewerBackend::next_poll_in(self)
+    }
+
+    fn read(&self) -> Result<&AnalogValues, ViewerBackendError> {
+        ViewerBackend::read(self)
+    }
+
+    fn value_revision(&self) -> u64 {
+        ViewerBackend::value_revision(self)
+    }
+
+    fn stats(&self) -> PollStats {
+        ViewerBackend::stats(self)
+    }
+
+    fn reconnect(&mut self) -> Result<(), ViewerBackendError> {
+        ViewerBackend::reconnect(self)
+    }
+
+    fn shutdown(&mut self) -> Result<(), ViewerBackendError> {
+        ViewerBackend::shutdown(self)
+    }
+
+    fn connection_state(&self) -> ConnectionState {
+        ViewerBackend::connection_state(self)
+    }
+
+    fn connection_history(&self) -> Vec<ConnectionEvent> {
+        ViewerBackend::connection_history(self)
+    }
+
+    fn backend(&self) -> Option<&ViewerBackend> {
+        Some(self)
+    }
+
+    fn backend_mut(&mut self) -> Option<&mut ViewerBackend> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::MockBackend;
+    use std::sync::Arc;
+
+    #[test]
+    fn poisoned_lock_is_recovered_and_cleared() {
+        let source: Arc<RwLock<Box<dyn AnalogSource>>> =
+            Arc::new(RwLock::new(Box::new(MockBackend::new())));
+        let holder = source.clone();
+        let panicked = std::thread::spawn(move || {
+            let _be = holder.write().unwrap();
+            panic!("poll went wrong");
+        })
+        .join();
+        assert!(panicked.is_err());
+        assert!(source.is_poisoned());
+
+        write_source(&source).poll().unwrap();
+        assert!(!source.is_poisoned());
+        assert_eq!(read_source(&source).stats().polls, 1);
+    }
+}
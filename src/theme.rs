@@ -0,0 +1,146 @@
+use std::path::Path;
+
+use serde::{Deserialize, Deserializer};
+
+/// a `#rrggbb` or `#rrggbbaa` color from a theme file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct HexColor(pub slint::Color);
+
+impl HexColor {
+    const fn rgb(r: u8, g: u8, b: u8) -> Self {
+        HexColor(slint::Color::from_rgb_u8(r, g, b))
+    }
+
+    fn parse(s: &str) -> Result<Self, String> {
+        let hex = s
+            .trim()
+            .strip_prefix('#')
+            .ok_or(format!("color {:?} doesn't start with #", s))?;
+
+        let channel = |i: usize| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or(format!("invalid color {:?}", s))
+        };
+
+        match hex.len() {
+            6 => Ok(HexColor(slint::Color::from_rgb_u8(
+                channel(0)?,
+                channel(2)?,
+                channel(4)?,
+            ))),
+            8 => Ok(HexColor(slint::Color::from_argb_u8(
+                channel(6)?,
+                channel(0)?,
+                channel(2)?,
+                channel(4)?,
+            ))),
+            _ => Err(format!("color {:?} is not #rrggbb or #rrggbbaa", s)),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for HexColor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        HexColor::parse(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+/// colors, fonts and sizes of the whole window
+/// the default matches themes/default.toml, a theme file only needs the keys it changes
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Theme {
+    /// window background, the platform style's own if unset
+    pub background: Option<HexColor>,
+    pub text: HexColor,
+    /// empty for the platform default font
+    pub font_family: String,
+    /// the main reading under each gauge, in px
+    pub value_font_size: f32,
+    /// secondary lines under each gauge, in px
+    pub detail_font_size: f32,
+    pub gauge_size: f32,
+    pub spacing: f32,
+    /// outline of the vector gauge
+    pub dial: HexColor,
+    /// needle of the vector gauge
+    pub needle: HexColor,
+    /// needle hub of the vector gauge
+    pub hub: HexColor,
+    /// reference markers and deviation text
+    pub reference: HexColor,
+    /// lit indicators and anything that needs attention
+    pub alarm: HexColor,
+    /// a healthy connection
+    pub healthy: HexColor,
+    /// unlit indicators
+    pub inactive: HexColor,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            background: None,
+            text: HexColor::rgb(0x00, 0x00, 0xff),
+            font_family: String::new(),
+            value_font_size: 25.0,
+            detail_font_size: 15.0,
+            gauge_size: 200.0,
+            spacing: 25.0,
+            dial: HexColor::rgb(0x00, 0xc0, 0xff),
+            needle: HexColor::rgb(0x64, 0xa8, 0xf0),
+            hub: HexColor::rgb(0x33, 0x33, 0x33),
+            reference: HexColor::rgb(0xff, 0xa5, 0x00),
+            alarm: HexColor::rgb(0xff, 0x00, 0x00),
+            healthy: HexColor::rgb(0x00, 0xc0, 0x00),
+            inactive: HexColor::rgb(0xcc, 0xcc, 0xcc),
+        }
+    }
+}
+
+impl Theme {
+    /// the built-in dark look, matches themes/dark.toml
+    pub fn dark() -> Self {
+        Theme {
+            background: Some(HexColor::rgb(0x1b, 0x1d, 0x23)),
+            text: HexColor::rgb(0x9e, 0xcb, 0xff),
+            dial: HexColor::rgb(0x3a, 0x8f, 0xd0),
+            needle: HexColor::rgb(0x64, 0xa8, 0xf0),
+            hub: HexColor::rgb(0xaa, 0xaa, 0xaa),
+            reference: HexColor::rgb(0xff, 0xb3, 0x40),
+            alarm: HexColor::rgb(0xff, 0x55, 0x55),
+            healthy: HexColor::rgb(0x40, 0xd0, 0x40),
+            inactive: HexColor::rgb(0x55, 0x55, 0x55),
+            ..Theme::default()
+        }
+    }
+
+    /// read a theme from a TOML file
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| format!("could not read {}: {:?}", path.display(), e))?;
+        toml::from_str(&contents).map_err(|e| format!("invalid theme {}: {}", path.display(), e))
+    }
+
+    /// load the theme named by `GAUGE_THEME`, falling back to the default one
+    pub fn from_env() -> Self {
+        Self::from_var("GAUGE_THEME", Theme::default)
+    }
+
+    /// load the theme the dark mode toggle switches to from `GAUGE_THEME_DARK`, or the built-in dark one
+    pub fn dark_from_env() -> Self {
+        Self::from_var("GAUGE_THEME_DARK", Theme::dark)
+    }
+
+    fn from_var(var: &str, fallback: fn() -> Self) -> Self {
+        match std::env::var_os(var) {
+            Some(path) => Self::load(Path::new(&path)).unwrap_or_else(|e| {
+                log::warn!("{}, using the built-in theme", e);
+                fallback()
+            }),
+            None => fallback(),
+        }
+    }
+}
@@ -0,0 +1,161 @@
+use std::fs::File;
+use std::io::{self, Read};
+use std::net::{SocketAddr, UdpSocket};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// where the backend gets its frames from
+pub trait Transport: Send + Sync {
+    /// point the transport at the device
+    /// sources that aren't addressed ignore this
+    fn connect(&mut self, _addr: SocketAddr) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// send a request (`init`, `poll`) to the device
+    fn send(&mut self, msg: &[u8]) -> io::Result<()>;
+
+    /// receive one frame into `buf`, returning its length
+    /// `frame_len` is what the frame spec expects, stream sources read exactly that much
+    fn recv(&mut self, buf: &mut [u8], frame_len: usize) -> io::Result<usize>;
+
+    fn read_timeout(&self) -> io::Result<Option<Duration>>;
+
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()>;
+
+    /// whether the init handshake can go over it, one-way sources skip it
+    fn handshakes(&self) -> bool {
+        true
+    }
+
+    /// make recv() fail with `WouldBlock` instead of waiting when there's no frame yet
+    /// sources that can't do that always wait
+    fn set_nonblocking(&mut self, nonblocking: bool) -> io::Result<()> {
+        if nonblocking {
+            return Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "this source can't be read without blocking",
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Transport for UdpSocket {
+    fn connect(&mut self, addr: SocketAddr) -> io::Result<()> {
+        UdpSocket::connect(self, addr)
+    }
+
+    fn send(&mut self, msg: &[u8]) -> io::Result<()> {
+        UdpSocket::send(self, msg).map(|_| ())
+    }
+
+    /// a datagram is a whole frame, however long it is
+    fn recv(&mut self, buf: &mut [u8], _frame_len: usize) -> io::Result<usize> {
+        UdpSocket::recv(self, buf)
+    }
+
+    fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        UdpSocket::read_timeout(self)
+    }
+
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        UdpSocket::set_read_timeout(self, timeout)
+    }
+
+    fn set_nonblocking(&mut self, nonblocking: bool) -> io::Result<()> {
+        UdpSocket::set_nonblocking(self, nonblocking)
+    }
+}
+
+/// reads binary frames written to a named pipe by another process
+/// the producer sets the pace, requests sent to it are dropped
+pub struct FifoTransport {
+    path: PathBuf,
+    fifo: File,
+}
+
+impl FifoTransport {
+    /// open the pipe for reading, this blocks until a writer opens it too
+    pub fn open(path: &Path) -> io::Result<Self> {
+        log::info!("waiting for a writer on {}", path.display());
+        let fifo = File::open(path)?;
+
+        Ok(FifoTransport {
+            path: path.to_path_buf(),
+            fifo,
+        })
+    }
+}
+
+impl Transport for FifoTransport {
+    fn send(&mut self, _msg: &[u8]) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn handshakes(&self) -> bool {
+        false
+    }
+
+    /// read exactly one frame, reopening the pipe if the writer went away
+    /// a partial frame left by a closing writer is discarded
+    fn recv(&mut self, buf: &mut [u8], frame_len: usize) -> io::Result<usize> {
+        loop {
+            match self.fifo.read_exact(&mut buf[..frame_len]) {
+                Ok(()) => return Ok(frame_len),
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                    log::info!("writer closed {}, reopening", self.path.display());
+                    self.fifo = File::open(&self.path)?;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// pipes block until the writer sends something, there is no timeout
+    fn read_timeout(&self) -> io::Result<Option<Duration>> {
+        Ok(None)
+    }
+
+    fn set_read_timeout(&mut self, _timeout: Option<Duration>) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fifo_reopens_when_the_writer_goes_away() {
+        use std::ffi::CString;
+        use std::io::Write;
+        use std::os::unix::ffi::OsStrExt;
+
+        let path = std::env::temp_dir().join(format!("fifo-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let name = CString::new(path.as_os_str().as_bytes()).unwrap();
+        assert_eq!(unsafe { libc::mkfifo(name.as_ptr(), 0o600) }, 0);
+
+        let writer_path = path.clone();
+        let writer = std::thread::spawn(move || {
+            for frame in [[1, 2, 3, 4], [5, 6, 7, 8]] {
+                let mut fifo = File::options().write(true).open(&writer_path).unwrap();
+                fifo.write_all(&frame).unwrap();
+                drop(fifo);
+                // so the reader sees this writer go before the next one opens
+                std::thread::sleep(Duration::from_millis(50));
+            }
+        });
+
+        let mut fifo = FifoTransport::open(&path).unwrap();
+        let mut buf = [0; 8];
+        assert_eq!(fifo.recv(&mut buf, 4).unwrap(), 4);
+        assert_eq!(buf[..4], [1, 2, 3, 4]);
+        assert_eq!(fifo.recv(&mut buf, 4).unwrap(), 4);
+        assert_eq!(buf[..4], [5, 6, 7, 8]);
+
+        writer.join().unwrap();
+        let _ = std::fs::remove_file(&path);
+    }
+}